@@ -0,0 +1,277 @@
+//! Read and decode a device's application layer (AL) state and error code.
+
+use std::{fmt, time::Duration};
+
+use ethercrab::{Command, MainDevice, SubDeviceRef};
+use serde::Serialize;
+
+use tokio::time::Instant;
+
+use crate::{register, Error};
+
+pub const INIT: u8 = 1;
+pub const PRE_OP: u8 = 2;
+pub const BOOT: u8 = 3;
+pub const SAFE_OP: u8 = 4;
+pub const OP: u8 = 8;
+
+const STATUS_STATE: u16 = 0x0f;
+const STATUS_ERROR: u16 = 1 << 4;
+/// Set in AL control to clear the error flag.
+pub const CONTROL_ACKNOWLEDGE: u16 = 1 << 4;
+/// How often to check whether a requested state has been reached.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// AL Status and AL Status Code, 0x0130 and 0x0134.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AlStatus {
+    /// One of [`INIT`], [`PRE_OP`], [`BOOT`], [`SAFE_OP`], or [`OP`].
+    pub state: u8,
+    /// The device refused or fell out of a state; `code` says why.
+    pub error: bool,
+    pub code: u16,
+}
+
+impl AlStatus {
+    pub fn new(status: u16, code: u16) -> Self {
+        Self {
+            state: (status & STATUS_STATE) as u8,
+            error: status & STATUS_ERROR != 0,
+            code,
+        }
+    }
+}
+
+impl fmt::Display for AlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match state_name(self.state) {
+            Some(name) => write!(f, "{name}")?,
+            None => write!(f, "{:#x}", self.state)?,
+        }
+        if self.error {
+            write!(f, "+ERR")?;
+        }
+        if self.code != 0 {
+            write!(f, " code:{:#06x}", self.code)?;
+            if let Some(description) = code_description(self.code) {
+                write!(f, " ({description})")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn state_name(state: u8) -> Option<&'static str> {
+    Some(match state {
+        INIT => "INIT",
+        PRE_OP => "PRE-OP",
+        BOOT => "BOOT",
+        SAFE_OP => "SAFE-OP",
+        OP => "OP",
+        _ => return None,
+    })
+}
+
+/// The state for a name like `safe-op` or `SAFE-OP`.
+pub fn parse_state(name: &str) -> Option<u8> {
+    Some(match name.to_ascii_lowercase().replace('_', "-").as_str() {
+        "init" => INIT,
+        "pre-op" | "preop" => PRE_OP,
+        "boot" => BOOT,
+        "safe-op" | "safeop" => SAFE_OP,
+        "op" => OP,
+        _ => return None,
+    })
+}
+
+/// What an AL status code means, per ETG.1000.6.
+pub fn code_description(code: u16) -> Option<&'static str> {
+    Some(match code {
+        0x0000 => "no error",
+        0x0001 => "unspecified error",
+        0x0002 => "no memory",
+        0x0003 => "invalid device setup",
+        0x0011 => "invalid requested state change",
+        0x0012 => "unknown requested state",
+        0x0013 => "bootstrap not supported",
+        0x0014 => "no valid firmware",
+        0x0015 => "invalid mailbox configuration for BOOT",
+        0x0016 => "invalid mailbox configuration for PRE-OP",
+        0x0017 => "invalid sync manager configuration",
+        0x0018 => "no valid inputs available",
+        0x0019 => "no valid outputs",
+        0x001a => "synchronization error",
+        0x001b => "sync manager watchdog",
+        0x001c => "invalid sync manager types",
+        0x001d => "invalid output configuration",
+        0x001e => "invalid input configuration",
+        0x001f => "invalid watchdog configuration",
+        0x0020 => "device needs cold start",
+        0x0021 => "device needs INIT",
+        0x0022 => "device needs PRE-OP",
+        0x0023 => "device needs SAFE-OP",
+        0x0024 => "invalid input mapping",
+        0x0025 => "invalid output mapping",
+        0x0026 => "inconsistent settings",
+        0x0027 => "free run not supported",
+        0x0028 => "sync mode not supported",
+        0x0029 => "free run needs 3 buffer mode",
+        0x002a => "background watchdog",
+        0x002b => "no valid inputs and outputs",
+        0x002c => "fatal sync error",
+        0x002d => "no sync error",
+        0x0030 => "invalid DC sync configuration",
+        0x0031 => "invalid DC latch configuration",
+        0x0032 => "PLL error",
+        0x0033 => "DC sync IO error",
+        0x0034 => "DC sync timeout",
+        0x0035 => "DC invalid sync cycle time",
+        0x0036 => "DC SYNC0 cycle time",
+        0x0037 => "DC SYNC1 cycle time",
+        0x0041 => "AoE mailbox error",
+        0x0042 => "EoE mailbox error",
+        0x0043 => "CoE mailbox error",
+        0x0044 => "FoE mailbox error",
+        0x0045 => "SoE mailbox error",
+        0x004f => "VoE mailbox error",
+        0x0050 => "EEPROM no access",
+        0x0051 => "EEPROM error",
+        0x0060 => "device restarted locally",
+        0x0061 => "device identification value updated",
+        0x00f0 => "application controller available",
+        _ => return None,
+    })
+}
+
+/// Read the AL status and status code of `subdevice`.
+pub async fn read<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<AlStatus, Error> {
+    Ok(AlStatus::new(
+        subdevice.register_read(register::AL_STATUS).await?,
+        subdevice.register_read(register::AL_STATUS_CODE).await?,
+    ))
+}
+
+/// Read the AL status and status code of the device at `address`, for
+/// when the group it was in has been lost to a failed transition.
+pub async fn read_at(maindevice: &MainDevice<'_>, address: u16) -> Result<AlStatus, Error> {
+    Ok(AlStatus::new(
+        Command::fprd(address, register::AL_STATUS)
+            .receive(maindevice)
+            .await?,
+        Command::fprd(address, register::AL_STATUS_CODE)
+            .receive(maindevice)
+            .await?,
+    ))
+}
+
+/// Ask `subdevice` to go to `state`, acknowledging any error it's in.
+pub async fn request<S>(subdevice: &SubDeviceRef<'_, S>, state: u8) -> Result<(), Error> {
+    subdevice
+        .register_write(register::AL_CONTROL, u16::from(state) | CONTROL_ACKNOWLEDGE)
+        .await?;
+    Ok(())
+}
+
+/// Wait for `subdevice` to reach `state` or flag an error, giving up after
+/// `timeout`. Returns the last status read either way.
+pub async fn wait_for<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    state: u8,
+    timeout: Duration,
+) -> Result<AlStatus, Error> {
+    let start = Instant::now();
+    loop {
+        let status = read(subdevice).await?;
+        if status.state == state || status.error || start.elapsed() > timeout {
+            return Ok(status);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_status() {
+        assert_eq!(
+            AlStatus::new(0x0014, 0x001b),
+            AlStatus {
+                state: SAFE_OP,
+                error: true,
+                code: 0x001b,
+            }
+        );
+        // Only the state nibble and error flag count.
+        assert_eq!(
+            AlStatus::new(0xffe8, 0),
+            AlStatus {
+                state: OP,
+                error: false,
+                code: 0,
+            }
+        );
+        assert_eq!(AlStatus::new(0x0012, 0).state, PRE_OP);
+    }
+
+    #[test]
+    fn display() {
+        for (status, code, expected) in [
+            (0x0008, 0x0000, "OP"),
+            (0x0001, 0x0000, "INIT"),
+            (0x0012, 0x0000, "PRE-OP+ERR"),
+            (
+                0x0014,
+                0x001b,
+                "SAFE-OP+ERR code:0x001b (sync manager watchdog)",
+            ),
+            (0x0003, 0x0014, "BOOT code:0x0014 (no valid firmware)"),
+            (0x0016, 0x8001, "0x6+ERR code:0x8001"),
+        ] {
+            assert_eq!(AlStatus::new(status, code).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(code_description(0x0000), Some("no error"));
+        assert_eq!(code_description(0x0036), Some("DC SYNC0 cycle time"));
+        assert_eq!(
+            code_description(0x00f0),
+            Some("application controller available")
+        );
+        assert_eq!(code_description(0x0004), None);
+        assert_eq!(code_description(0x8001), None);
+    }
+
+    #[test]
+    fn parse_states() {
+        for (name, expected) in [
+            ("init", Some(INIT)),
+            ("INIT", Some(INIT)),
+            ("Pre-Op", Some(PRE_OP)),
+            ("preop", Some(PRE_OP)),
+            ("PRE_OP", Some(PRE_OP)),
+            ("boot", Some(BOOT)),
+            ("SAFE-OP", Some(SAFE_OP)),
+            ("safeop", Some(SAFE_OP)),
+            ("safe_op", Some(SAFE_OP)),
+            ("op", Some(OP)),
+            ("OP", Some(OP)),
+            ("", None),
+            ("operational", None),
+            ("safe op", None),
+            ("4", None),
+        ] {
+            assert_eq!(parse_state(name), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn parse_state_names() {
+        for state in [INIT, PRE_OP, BOOT, SAFE_OP, OP] {
+            assert_eq!(parse_state(state_name(state).unwrap()), Some(state));
+        }
+    }
+}
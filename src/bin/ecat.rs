@@ -0,0 +1,1063 @@
+//! Assorted EtherCAT network utilities
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use argh::FromArgs;
+use ecat_utils::{
+    al, coe, counters, dc, diag,
+    error::{Context, Error},
+    host,
+    quirk::Quirks,
+    register, reset,
+    runtime::Bus,
+    scaffold,
+    select::Selector,
+    shutdown::{self, TeardownGuard},
+    sii::{self, Sii, SiiError},
+};
+use ethercrab::{subdevice_group::PreOp, SubDeviceGroup, SubDeviceRef};
+
+/// Maximum number of SubDevices that can be stored. This must be a power of 2 greater than 1.
+const MAX_SUBDEVICES: usize = 128;
+/// Maximum total PDI length.
+const PDI_LEN: usize = 8192;
+/// How long to wait for a device's PDI to let go of its EEPROM.
+const EEPROM_ACCESS_TIMEOUT: Duration = Duration::from_secs(1);
+/// Where process RAM starts, which SyncManagers have to be in.
+const PROCESS_RAM_START: u16 = 0x1000;
+/// How long to wait for a device to reach a requested state.
+const STATE_TRANSITION_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Group = SubDeviceGroup<MAX_SUBDEVICES, PDI_LEN, PreOp>;
+
+#[derive(FromArgs)]
+/// Assorted EtherCAT network utilities.
+struct Cli {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Doctor(Doctor),
+    DcOffset(DcOffset),
+    State(State),
+    FactoryReset(FactoryReset),
+    Info(Info),
+    Alias(Alias),
+    Conformance(Conformance),
+    Scaffold(Scaffold),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "doctor")]
+/// Check that this host is set up to run an EtherCAT network.
+///
+/// Each problem found is printed with a command that fixes it. Exits
+/// non-zero if anything would stop the bus from working at all.
+struct Doctor {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dc-offset")]
+/// Measure how far the DC reference clock is from the host's clock.
+///
+/// The reference clock is the first device with a distributed clock.
+/// Each sample prints its offset from CLOCK_REALTIME and how fast that's
+/// drifting since the first sample.
+struct DcOffset {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(option, default = "1000")]
+    /// milliseconds between samples
+    interval_ms: u64,
+    #[argh(option)]
+    /// stop after this many samples instead of running until interrupted
+    count: Option<u32>,
+    #[argh(option)]
+    /// keep the latest offset in ns in this file, so other processes can
+    /// line their timestamps up with the bus
+    output: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "state")]
+/// Request an AL state on one device or the whole bus.
+///
+/// Reports the state each device ends up in and its AL status code, and
+/// exits non-zero if any didn't get there. Devices are left in that state
+/// on exit. Devices in SAFE-OP or OP expect process data to be cycled, so
+/// they may drop back out once nothing is driving the bus.
+struct State {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(positional, from_str_fn(parse_state))]
+    /// init, pre-op, boot, safe-op, or op
+    state: u8,
+    #[argh(positional)]
+    /// the device to change, as a name, glob, @address, #position, or
+    /// alias:n; every device if left out
+    device: Option<Selector>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "factory-reset")]
+/// Restore a device to its factory settings.
+///
+/// Picks the most specific recipe for the device from the built-in
+/// catalog and any given with --recipes, shows what it'll do, and asks
+/// before doing it.
+struct FactoryReset {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(positional)]
+    /// the device to reset, as a name, glob, @address, #position, or
+    /// alias:n
+    device: Selector,
+    #[argh(option)]
+    /// a TOML file of extra recipes, tried before the built-in ones
+    recipes: Option<PathBuf>,
+    #[argh(switch)]
+    /// don't ask for confirmation
+    yes: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "info")]
+/// Show everything worth knowing about one device when triaging it.
+///
+/// Prints its identity, AL state and status code, error counters, mailbox
+/// protocols, firmware versions, process data sizes, and newest
+/// diagnosis messages. Whatever can't be read is reported and skipped.
+struct Info {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(positional)]
+    /// the device to show, as a name, glob, @address, #position, or
+    /// alias:n
+    device: Selector,
+    #[argh(option, default = "5")]
+    /// how many diagnosis messages to show
+    messages: usize,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "alias")]
+/// Set a device's station alias, so it keeps its address however the
+/// bus is wired.
+///
+/// The alias is written to the EEPROM, which the device only reads at
+/// power-up, so this then asks for the device to be power-cycled and
+/// checks it took.
+struct Alias {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(positional)]
+    /// the device to set the alias of, as a name, glob, @address,
+    /// #position, or alias:n
+    device: Selector,
+    #[argh(positional, from_str_fn(parse_alias))]
+    /// the new alias, in decimal or 0x hex
+    alias: u16,
+    #[argh(switch)]
+    /// don't wait for a power-cycle to check the alias took
+    no_verify: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "check")]
+/// Run quick conformance checks on a device to catch integration problems
+/// early.
+///
+/// Checks the SII header, SyncManager layout, mailbox sizes, mandatory
+/// CoE objects, that the identity in 0x1018 agrees with the SII, and that
+/// the device goes to INIT and back. Exits non-zero if any check fails.
+struct Conformance {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(positional)]
+    /// the device to check, as a name, glob, @address, #position, or
+    /// alias:n
+    device: Selector,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "scaffold")]
+/// Generate a Rust project that drives the connected network with
+/// ethercrab, as a starting point for a machine's own program.
+///
+/// The project checks it's on the same devices, lays their process data
+/// out as structs per the PDO mapping they have now, and runs a cyclic
+/// loop over them in OP.
+struct Scaffold {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(option)]
+    /// the directory to create the project in; it mustn't already have one
+    out: PathBuf,
+}
+
+fn parse_alias(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("{s:?} isn't a 16-bit number"))
+}
+
+fn parse_state(name: &str) -> Result<u8, String> {
+    al::parse_state(name)
+        .ok_or_else(|| format!("{name:?} isn't one of init, pre-op, boot, safe-op, or op"))
+}
+
+#[tokio::main]
+async fn main() {
+    let cli: Cli = argh::from_env();
+
+    let run = async {
+        match cli.command {
+            Command::Doctor(doctor) => {
+                if !run_doctor(&doctor.interface) {
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+            Command::DcOffset(dc_offset) => run_dc_offset(&dc_offset).await,
+            Command::State(state) => run_state(&state).await,
+            Command::FactoryReset(factory_reset) => run_factory_reset(&factory_reset).await,
+            Command::Info(info) => run_info(&info).await,
+            Command::Alias(alias) => run_alias(&alias).await,
+            Command::Conformance(conformance) => run_conformance(&conformance).await,
+            Command::Scaffold(scaffold) => run_scaffold(&scaffold).await,
+        }
+    };
+    let Some(result) = shutdown::run_until_shutdown(run).await else {
+        std::process::exit(130);
+    };
+    if let Err(err) = result {
+        println!("{err}");
+        std::process::exit(1);
+    }
+}
+
+/// Open the bus on `interface` and bring every device up to PRE-OP.
+async fn open(interface: &str) -> Result<(Bus, TeardownGuard<'static, Group>), Error> {
+    let bus = Bus::open(interface)?;
+    let group = bus
+        .init::<MAX_SUBDEVICES, PDI_LEN>()
+        .await
+        .context("failed to init")?;
+    Ok((bus, group))
+}
+
+#[derive(PartialEq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    status: Status,
+    what: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(what: impl Into<String>) -> Self {
+        Self {
+            status: Status::Ok,
+            what: what.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(what: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warn,
+            what: what.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(what: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Fail,
+            what: what.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let status = match self.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        };
+        write!(f, "{status:<4} {}", self.what)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n     fix: {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Print the result of every check, returning false if any failed.
+fn run_doctor(interface: &str) -> bool {
+    let checks = doctor_checks(interface);
+    for check in &checks {
+        println!("{check}");
+    }
+    !checks.iter().any(|check| check.status == Status::Fail)
+}
+
+fn doctor_checks(interface: &str) -> Vec<Check> {
+    let mut checks = vec![];
+
+    checks.push(match host::has_raw_socket_capability() {
+        Ok(true) => Check::ok("raw sockets available"),
+        Ok(false) => Check::fail(
+            "no permission to open raw sockets",
+            "run as root or `sudo setcap cap_net_raw=pe <path to tool>`",
+        ),
+        Err(err) => Check::warn(
+            format!("couldn't read process capabilities: {err}"),
+            "run as root if opening the interface fails",
+        ),
+    });
+
+    if !host::interface_exists(interface) {
+        checks.push(Check::fail(
+            format!("no interface named {interface}"),
+            "list interfaces with `ip link`",
+        ));
+        return checks;
+    }
+
+    checks.push(match host::mtu(interface) {
+        Ok(mtu) if mtu >= 1500 => Check::ok(format!("MTU is {mtu}")),
+        Ok(mtu) => Check::fail(
+            format!("MTU is {mtu}, too small for full EtherCAT frames"),
+            format!("sudo ip link set {interface} mtu 1500"),
+        ),
+        Err(err) => Check::warn(
+            format!("couldn't read MTU: {err}"),
+            format!("check `ip link show {interface}`"),
+        ),
+    });
+
+    match host::offloads(interface) {
+        Ok(offloads) => {
+            for offload in offloads {
+                let Some((_, flag)) = host::LATENCY_OFFLOADS
+                    .iter()
+                    .find(|(name, _)| *name == offload.name)
+                else {
+                    continue;
+                };
+                checks.push(if !offload.enabled {
+                    Check::ok(format!("{} off", offload.name))
+                } else if offload.fixed {
+                    Check::warn(
+                        format!("{} is on and fixed by the driver", offload.name),
+                        "use a NIC whose driver allows disabling it",
+                    )
+                } else {
+                    Check::warn(
+                        format!("{} is on, which delays frames", offload.name),
+                        format!("sudo ethtool -K {interface} {flag} off"),
+                    )
+                });
+            }
+        }
+        Err(err) => checks.push(Check::warn(
+            format!("couldn't read offload settings: {err}"),
+            "install ethtool",
+        )),
+    }
+
+    match host::coalescing(interface) {
+        Ok(settings) => {
+            for (name, value) in settings {
+                if !host::LATENCY_COALESCING.contains(&name.as_str()) {
+                    continue;
+                }
+                checks.push(if value == 0 {
+                    Check::ok(format!("{name} is 0"))
+                } else {
+                    Check::warn(
+                        format!("{name} is {value}, which adds latency"),
+                        format!("sudo ethtool -C {interface} {name} 0"),
+                    )
+                });
+            }
+        }
+        // Plenty of drivers don't support coalescing at all.
+        Err(_) => checks.push(Check::ok("interrupt coalescing not supported")),
+    }
+
+    checks.push(if host::is_realtime_kernel() {
+        Check::ok("PREEMPT_RT kernel")
+    } else {
+        Check::warn(
+            "kernel isn't PREEMPT_RT; cycle times will jitter",
+            "install your distribution's realtime kernel, e.g. `linux-image-rt-amd64`",
+        )
+    });
+
+    checks
+}
+
+async fn run_dc_offset(args: &DcOffset) -> Result<(), Error> {
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+
+    let mut widths = vec![];
+    for subdevice in group.iter(maindevice) {
+        let features: u16 = subdevice
+            .register_read(register::ESC_FEATURES)
+            .await
+            .context(format!(
+                "failed to read the ESC features of {:#06x}",
+                subdevice.configured_address()
+            ))?;
+        widths.push(dc::width(features));
+    }
+    let Some(reference) = widths.iter().position(Option::is_some) else {
+        println!("no device on the bus has a distributed clock");
+        drop(group);
+        std::process::exit(1);
+    };
+    let width = widths[reference].expect("reference clock has a DC");
+    let reference = group.subdevice(maindevice, reference)?;
+    println!(
+        "reference clock is {:#06x} {} ({width})",
+        reference.configured_address(),
+        reference.name()
+    );
+    // Devices only keep as many bits of system time as their clock has, so
+    // with both widths on one bus they can't all agree on the upper half.
+    if widths.contains(&Some(dc::Width::Bits32)) && widths.contains(&Some(dc::Width::Bits64)) {
+        println!("warning: the bus mixes 32-bit and 64-bit DC devices; 32-bit devices:");
+        for (subdevice, _) in group
+            .iter(maindevice)
+            .zip(&widths)
+            .filter(|(_, width)| **width == Some(dc::Width::Bits32))
+        {
+            println!(
+                "  {:#06x} {}",
+                subdevice.configured_address(),
+                subdevice.name()
+            );
+        }
+    }
+
+    let mut first = None;
+    let mut samples = 0;
+    while args.count.is_none_or(|count| samples < count) {
+        let before = SystemTime::now();
+        let dc_time = match width {
+            dc::Width::Bits32 => reference
+                .register_read::<u32>(register::DC_SYSTEM_TIME)
+                .await
+                .map(u64::from),
+            dc::Width::Bits64 => reference.register_read(register::DC_SYSTEM_TIME).await,
+        }
+        .context("failed to read the DC system time")?;
+        let round_trip = SystemTime::now().duration_since(before).unwrap_or_default();
+        // Assume the register was sampled halfway through the round trip.
+        let host_time = before + round_trip / 2;
+        let host_dc_time = dc::ethercat_time(host_time).ok_or_else(|| {
+            Error::Other("host clock is before 2000, the EtherCAT epoch; set it first".into())
+        })?;
+        let offset = dc::difference(dc_time, host_dc_time, width);
+
+        let (first_offset, first_time) = *first.get_or_insert((offset, host_time));
+        let elapsed = host_time
+            .duration_since(first_time)
+            .unwrap_or_default()
+            .as_nanos();
+        let drift = if elapsed == 0 {
+            0.0
+        } else {
+            (offset - first_offset) as f64 / elapsed as f64 * 1e6
+        };
+        println!(
+            "offset:{offset}ns drift:{drift:+.3}ppm round-trip:{}us",
+            round_trip.as_micros()
+        );
+
+        if let Some(path) = &args.output {
+            write_replacing(path, &format!("{offset}\n"))
+                .context(format!("failed to write {}", path.display()))?;
+        }
+
+        samples += 1;
+        tokio::time::sleep(Duration::from_millis(args.interval_ms)).await;
+    }
+
+    bus.close(group).await?;
+    Ok(())
+}
+
+async fn run_state(args: &State) -> Result<(), Error> {
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+    // Devices are left in the requested state, not taken back down.
+    let group = group.into_inner();
+    let subdevices: Vec<_> = match &args.device {
+        Some(selector) => vec![selector.resolve(group.iter(maindevice))?],
+        None => group.iter(maindevice).collect(),
+    };
+
+    let mut failed = false;
+    for subdevice in &subdevices {
+        let address = subdevice.configured_address();
+        al::request(subdevice, args.state).await.context(format!(
+            "failed to request a state change of {address:#06x}"
+        ))?;
+        let timeout = bus
+            .quirks(subdevice)
+            .state_timeout(STATE_TRANSITION_TIMEOUT);
+        let status = al::wait_for(subdevice, args.state, timeout)
+            .await
+            .context(format!("failed to read the AL status of {address:#06x}"))?;
+        println!("{address:#06x} {} {status}", subdevice.name());
+        failed |= status.state != args.state || status.error;
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn run_factory_reset(args: &FactoryReset) -> Result<(), Error> {
+    let mut recipes = match &args.recipes {
+        Some(path) => reset::load(path).context(format!("failed to load {}", path.display()))?,
+        None => vec![],
+    };
+    recipes.extend(reset::builtin());
+
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+    let subdevice = args.device.resolve(group.iter(maindevice))?;
+    let address = subdevice.configured_address();
+    let Some(recipe) = reset::find(&recipes, &subdevice.identity()) else {
+        println!("no recipe for {address:#06x} {}", subdevice.name());
+        drop(group);
+        std::process::exit(1);
+    };
+
+    println!("{address:#06x} {}: {}", subdevice.name(), recipe.name);
+    for step in &recipe.steps {
+        println!(
+            "  write {:#010x} to {:#06x}:{:02x}",
+            step.value, step.index, step.sub_index
+        );
+    }
+    if !args.yes {
+        print!("type yes to reset: ");
+        std::io::stdout().flush().context("failed to prompt")?;
+        let mut answer = String::new();
+        std::io::stdin()
+            .read_line(&mut answer)
+            .context("failed to read the answer")?;
+        if answer.trim() != "yes" {
+            println!("not reset");
+            return Ok(());
+        }
+    }
+
+    recipe
+        .run(&subdevice, &bus.quirks(&subdevice))
+        .await
+        .context(format!("failed to reset {address:#06x}"))?;
+    println!("reset");
+    if let Some(note) = &recipe.note {
+        println!("note: {note}");
+    }
+
+    bus.close(group).await?;
+    Ok(())
+}
+
+async fn run_info(args: &Info) -> Result<(), Error> {
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+    let subdevice = args.device.resolve(group.iter(maindevice))?;
+
+    let identity = subdevice.identity();
+    println!(
+        "{:#06x} {}",
+        subdevice.configured_address(),
+        subdevice.name()
+    );
+    println!(
+        "  identity: vendor:{:#010x} product:{:#010x} rev:{:#010x} serial:{} alias:{:#06x}",
+        identity.vendor_id,
+        identity.product_id,
+        identity.revision,
+        identity.serial,
+        subdevice.alias_address()
+    );
+    match al::read(&subdevice).await {
+        Ok(status) => println!("  state: {status}"),
+        Err(err) => println!("  state: unavailable ({err})"),
+    }
+    match counters::read(&subdevice).await {
+        Ok(errors) => {
+            let nonzero: Vec<_> = errors
+                .nonzero()
+                .iter()
+                .map(|(name, count)| format!("{name}={count}"))
+                .collect();
+            if nonzero.is_empty() {
+                println!("  errors: none");
+            } else {
+                println!("  errors: {}", nonzero.join(","));
+            }
+        }
+        Err(err) => println!("  errors: unavailable ({err})"),
+    }
+
+    let protocols = match sii::with_access(
+        &subdevice,
+        EEPROM_ACCESS_TIMEOUT,
+        sii::read_header(&subdevice),
+    )
+    .await
+    {
+        Ok(header) => header.protocol_names(),
+        Err(err) => {
+            println!("  mailbox: unavailable ({err})");
+            vec![]
+        }
+    };
+    if !protocols.is_empty() {
+        println!("  mailbox: {}", protocols.join(","));
+    }
+    // Everything else lives in the object dictionary.
+    if !protocols.contains(&"CoE") {
+        println!("  no CoE, so no firmware versions, process data sizes, or diagnosis");
+    } else {
+        for (what, index) in [("hardware", 0x1009), ("software", 0x100a)] {
+            match subdevice.sdo_read::<heapless::String<64>>(index, 0).await {
+                Ok(version) => println!("  {what}: {}", version.trim()),
+                Err(err) => println!("  {what}: unavailable ({})", Error::from(err)),
+            }
+        }
+        let inputs = coe::assigned_bits(&subdevice, coe::TX_PDO_ASSIGN).await;
+        let outputs = coe::assigned_bits(&subdevice, coe::RX_PDO_ASSIGN).await;
+        match (inputs, outputs) {
+            (Ok(inputs), Ok(outputs)) => println!(
+                "  pdo: in:{}B out:{}B",
+                inputs.div_ceil(8),
+                outputs.div_ceil(8)
+            ),
+            (Err(err), _) | (_, Err(err)) => println!("  pdo: unavailable ({err})"),
+        }
+        match diag::history(&subdevice, args.messages).await {
+            Ok(messages) if messages.is_empty() => println!("  diagnosis: none"),
+            Ok(messages) => {
+                println!("  diagnosis:");
+                for message in messages {
+                    println!("    {message}");
+                }
+            }
+            Err(err) => println!("  diagnosis: unavailable ({err})"),
+        }
+    }
+
+    bus.close(group).await?;
+    Ok(())
+}
+
+async fn run_alias(args: &Alias) -> Result<(), Error> {
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+    let subdevice = args.device.resolve(group.iter(maindevice))?;
+    let address = subdevice.configured_address();
+    let position = group
+        .iter(maindevice)
+        .position(|other| other.configured_address() == address)
+        .expect("resolved from this group");
+
+    let config = sii::acquire(&subdevice, EEPROM_ACCESS_TIMEOUT, false)
+        .await
+        .context(format!("failed to get the EEPROM of {address:#06x}"))?;
+    let written = sii::write_alias(&subdevice, args.alias).await;
+    sii::release(&subdevice, config)
+        .await
+        .context(format!("failed to hand back the EEPROM of {address:#06x}"))?;
+    written.context(format!("failed to write the alias of {address:#06x}"))?;
+    println!(
+        "{address:#06x} {}: wrote alias {:#06x} to the EEPROM",
+        subdevice.name(),
+        args.alias
+    );
+
+    bus.close(group).await?;
+    if args.no_verify {
+        return Ok(());
+    }
+
+    print!("power-cycle the device, then press enter: ");
+    std::io::stdout().flush().context("failed to prompt")?;
+    std::io::stdin()
+        .read_line(&mut String::new())
+        .context("failed to read the answer")?;
+    let group = bus
+        .init::<MAX_SUBDEVICES, PDI_LEN>()
+        .await
+        .context("failed to init after the power-cycle")?;
+    let subdevice = group.subdevice(maindevice, position).context(format!(
+        "no device at position {position} after the power-cycle"
+    ))?;
+    let alias: u16 = subdevice
+        .register_read(register::STATION_ALIAS)
+        .await
+        .context("failed to read the alias back")?;
+    let name = subdevice.name().to_string();
+    bus.close(group).await?;
+    if alias != args.alias {
+        println!(
+            "#{position} {name} has alias {alias:#06x}, not {:#06x}; was it power-cycled?",
+            args.alias
+        );
+        std::process::exit(1);
+    }
+    println!("#{position} {name} now has alias {alias:#06x}");
+    Ok(())
+}
+
+async fn run_conformance(args: &Conformance) -> Result<(), Error> {
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+    let subdevice = args.device.resolve(group.iter(maindevice))?;
+
+    println!(
+        "{:#06x} {}",
+        subdevice.configured_address(),
+        subdevice.name()
+    );
+    let checks = conformance_checks(&subdevice, &bus.quirks(&subdevice)).await;
+    for check in &checks {
+        println!("{check}");
+    }
+
+    bus.close(group).await?;
+    if checks.iter().any(|check| check.status == Status::Fail) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn conformance_checks<S>(subdevice: &SubDeviceRef<'_, S>, quirks: &Quirks) -> Vec<Check> {
+    let mut checks = vec![];
+
+    let sii = match sii::with_access(subdevice, EEPROM_ACCESS_TIMEOUT, sii::read_image(subdevice))
+        .await
+        .and_then(|image| Sii::parse(&image))
+    {
+        Ok(sii) => sii,
+        Err(SiiError::PdiAccess) => {
+            checks.push(Check::fail(
+                "EEPROM is held by the PDI, so the SII couldn't be checked",
+                "retry once the device's firmware lets go of it, or use `siicat --force-access`",
+            ));
+            return checks;
+        }
+        Err(err) => {
+            checks.push(Check::fail(
+                format!("couldn't read the SII: {err}"),
+                "look at the EEPROM with `siicat`",
+            ));
+            return checks;
+        }
+    };
+    checks.push(if sii.header.checksum_ok {
+        Check::ok("SII header checksum")
+    } else {
+        Check::fail(
+            "SII header checksum is wrong",
+            "rewrite the EEPROM with `siicat --write`",
+        )
+    });
+
+    let mut sync_managers_ok = true;
+    let mut used: Vec<(usize, u16, u16)> = vec![];
+    for (i, sm) in sii.sync_managers.iter().enumerate() {
+        if sm.kind == 0 || sm.length == 0 {
+            continue;
+        }
+        let end = sm.start_address.saturating_add(sm.length);
+        if sm.start_address < PROCESS_RAM_START {
+            checks.push(Check::fail(
+                format!(
+                    "sm{i} starts at {:#06x}, below process RAM",
+                    sm.start_address
+                ),
+                "fix the SyncM category of the SII",
+            ));
+            sync_managers_ok = false;
+        }
+        for (j, start, other_end) in &used {
+            if sm.start_address < *other_end && *start < end {
+                checks.push(Check::fail(
+                    format!("sm{i} overlaps sm{j}"),
+                    "fix the SyncM category of the SII",
+                ));
+                sync_managers_ok = false;
+            }
+        }
+        used.push((i, sm.start_address, end));
+    }
+    if sync_managers_ok {
+        checks.push(Check::ok(format!("{} SyncManagers laid out", used.len())));
+    }
+
+    let mailbox = sii.header.standard_mailbox;
+    if mailbox.receive_size != 0 {
+        for (i, what, start, length) in [
+            (0, "out", mailbox.receive_offset, mailbox.receive_size),
+            (1, "in", mailbox.send_offset, mailbox.send_size),
+        ] {
+            let config: Result<[u8; 8], _> = subdevice
+                .register_read(register::SYNC_MANAGER + 8 * i)
+                .await;
+            checks.push(match config {
+                Ok([s0, s1, l0, l1, ..])
+                    if (u16::from_le_bytes([s0, s1]), u16::from_le_bytes([l0, l1]))
+                        == (start, length) =>
+                {
+                    Check::ok(format!("mailbox {what} is {length}B at {start:#06x}"))
+                }
+                Ok([s0, s1, l0, l1, ..]) => Check::fail(
+                    format!(
+                        "sm{i} (mailbox {what}) is {}B at {:#06x}, but the SII says {length}B at {start:#06x}",
+                        u16::from_le_bytes([l0, l1]),
+                        u16::from_le_bytes([s0, s1])
+                    ),
+                    "make the SII header agree with the SyncM category",
+                ),
+                Err(err) => Check::fail(
+                    format!("couldn't read sm{i}: {}", Error::from(err)),
+                    "check the device is still on the bus",
+                ),
+            });
+        }
+    }
+
+    if !sii.header.protocol_names().contains(&"CoE") {
+        checks.push(Check::ok("no CoE, so no object dictionary to check"));
+    } else {
+        // The device type is a UDINT, and 0x1018:0 the number of identity
+        // entries.
+        let device_type = subdevice.sdo_read::<u32>(0x1000, 0).await.map(|_| ());
+        let identity = subdevice.sdo_read::<u8>(0x1018, 0).await.map(|_| ());
+        for (index, what, read) in [
+            (0x1000, "device type", device_type),
+            (0x1018, "identity", identity),
+        ] {
+            checks.push(match read {
+                Ok(()) => Check::ok(format!("{index:#06x} {what} present")),
+                Err(err) => Check::fail(
+                    format!("{index:#06x} {what} missing: {}", Error::from(err)),
+                    "every CoE device has to have it; ask the vendor",
+                ),
+            });
+        }
+        let identity = sii.header.identity;
+        for (sub_index, what, expected) in [
+            (1, "vendor", identity.vendor_id),
+            (2, "product", identity.product_id),
+            (3, "revision", identity.revision),
+        ] {
+            checks.push(match subdevice.sdo_read::<u32>(0x1018, sub_index).await {
+                Ok(actual) if actual == expected => {
+                    Check::ok(format!("0x1018 {what} matches the SII"))
+                }
+                Ok(actual) => Check::fail(
+                    format!("0x1018 {what} is {actual:#010x}, but the SII says {expected:#010x}"),
+                    "update the EEPROM or firmware so they agree",
+                ),
+                Err(err) => Check::fail(
+                    format!("couldn't read the 0x1018 {what}: {}", Error::from(err)),
+                    "every CoE device has to have it; ask the vendor",
+                ),
+            });
+        }
+    }
+
+    for (from, to) in [(al::PRE_OP, al::INIT), (al::INIT, al::PRE_OP)] {
+        let transition = format!(
+            "{} -> {}",
+            al::state_name(from).unwrap_or("?"),
+            al::state_name(to).unwrap_or("?")
+        );
+        let status = match al::request(subdevice, to).await {
+            Ok(()) => {
+                al::wait_for(
+                    subdevice,
+                    to,
+                    quirks.state_timeout(STATE_TRANSITION_TIMEOUT),
+                )
+                .await
+            }
+            Err(err) => Err(err),
+        };
+        checks.push(match status {
+            Ok(status) if status.state == to && !status.error => Check::ok(transition),
+            Ok(status) => Check::fail(
+                format!("{transition} ended in {status}"),
+                "look up the AL status code in the device's manual",
+            ),
+            Err(err) => Check::fail(
+                format!("{transition} failed: {err}"),
+                "check the device is still on the bus",
+            ),
+        });
+    }
+
+    checks
+}
+
+async fn run_scaffold(args: &Scaffold) -> Result<(), Error> {
+    let manifest = args.out.join("Cargo.toml");
+    if manifest.exists() {
+        println!("{} already exists", manifest.display());
+        std::process::exit(1);
+    }
+
+    let (bus, group) = open(&args.interface).await?;
+    let maindevice = bus.maindevice();
+    let mut devices = vec![];
+    for subdevice in group.iter(maindevice) {
+        devices.push(scan(&subdevice).await);
+    }
+    bus.close(group).await?;
+    if devices.is_empty() {
+        println!("no devices found on {}", args.interface);
+        std::process::exit(1);
+    }
+
+    let src = args.out.join("src");
+    std::fs::create_dir_all(&src).context(format!("failed to create {}", src.display()))?;
+    let package = scaffold::package_name(&args.out);
+    std::fs::write(&manifest, scaffold::cargo_toml(&package))
+        .context(format!("failed to write {}", manifest.display()))?;
+    let main = src.join("main.rs");
+    std::fs::write(&main, scaffold::main_rs(&args.interface, &devices))
+        .context(format!("failed to write {}", main.display()))?;
+    println!(
+        "wrote {package} for {} devices to {}",
+        devices.len(),
+        args.out.display()
+    );
+    Ok(())
+}
+
+/// What `ecat scaffold` needs to know about a device. The PDO mapping
+/// comes from CoE where the device has it, since that's what it'll use in
+/// OP, and otherwise from the SII, which also names the entries.
+async fn scan<S>(subdevice: &SubDeviceRef<'_, S>) -> scaffold::Device {
+    let address = subdevice.configured_address();
+    let sii = match sii::with_access(subdevice, EEPROM_ACCESS_TIMEOUT, sii::read_image(subdevice))
+        .await
+        .and_then(|image| Sii::parse(&image))
+    {
+        Ok(sii) => Some(sii),
+        Err(err) => {
+            eprintln!(
+                "{address:#06x} {}: couldn't read the SII: {err}",
+                subdevice.name()
+            );
+            None
+        }
+    };
+    let sii_entries = |pdos: &[sii::Pdo]| -> Vec<scaffold::Entry> {
+        pdos.iter()
+            // PDOs not assigned to a SyncManager aren't in the process data.
+            .filter(|pdo| pdo.sync_manager != 0xff)
+            .flat_map(|pdo| &pdo.entries)
+            .map(|entry| scaffold::Entry {
+                index: entry.index,
+                sub_index: entry.sub_index,
+                bits: entry.bit_len.into(),
+                data_type: entry.data_type,
+                name: entry.name.clone(),
+            })
+            .collect()
+    };
+    let (mut inputs, mut outputs) = match &sii {
+        Some(sii) => (sii_entries(&sii.tx_pdos), sii_entries(&sii.rx_pdos)),
+        None => (vec![], vec![]),
+    };
+
+    let has_coe = sii
+        .as_ref()
+        .is_some_and(|sii| sii.header.protocol_names().contains(&"CoE"));
+    if has_coe {
+        let mapped = (
+            coe::assigned_entries(subdevice, coe::TX_PDO_ASSIGN).await,
+            coe::assigned_entries(subdevice, coe::RX_PDO_ASSIGN).await,
+        );
+        match mapped {
+            (Ok(tx), Ok(rx)) => {
+                // The SII still has the names and data types.
+                let described: Vec<_> = inputs.iter().chain(&outputs).cloned().collect();
+                let entry = |mapped: &coe::Mapped| {
+                    let described = described.iter().find(|entry| {
+                        (entry.index, entry.sub_index) == (mapped.index, mapped.sub_index)
+                    });
+                    scaffold::Entry {
+                        index: mapped.index,
+                        sub_index: mapped.sub_index,
+                        bits: mapped.bits.into(),
+                        data_type: described.map_or(0, |entry| entry.data_type),
+                        name: described.and_then(|entry| entry.name.clone()),
+                    }
+                };
+                inputs = tx.iter().map(entry).collect();
+                outputs = rx.iter().map(entry).collect();
+            }
+            (Err(err), _) | (_, Err(err)) => eprintln!(
+                "{address:#06x} {}: couldn't read the PDO mapping, so using the SII's: {err}",
+                subdevice.name()
+            ),
+        }
+    }
+
+    scaffold::Device {
+        name: subdevice.name().to_string(),
+        address,
+        identity: subdevice.identity(),
+        inputs,
+        outputs,
+    }
+}
+
+/// Replace `path` in one step, so readers never see a partial write.
+fn write_replacing(path: &Path, contents: &str) -> std::io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)
+}
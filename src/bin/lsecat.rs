@@ -1,25 +1,48 @@
 //! List the devices visibile on the EtherCAT network
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    io::IsTerminal,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use argh::FromArgs;
+use ecat_utils::{
+    al::{self, AlStatus},
+    budget::Budget,
+    counters::{self, ErrorCounters},
+    dc::{self, DcStatus},
+    error::Context,
+    esc::{self, EscInfo},
+    host::{self, NicTuning},
+    mailbox::{self, MailboxSizes},
+    network::{self, Network},
+    register,
+    runtime::{Bus, MAX_PDI_LEN, MAX_PDU_DATA, MAX_PDU_PAYLOAD},
+    select::Selector,
+    shutdown::{self, TeardownGuard},
+    topology,
+};
 use ethercrab::{
-    error::Error,
-    std::{ethercat_now, tx_rx_task},
-    subdevice_group::{Init, Op},
-    MainDevice, MainDeviceConfig, PduStorage, SubDeviceGroup, SubDeviceIdentity, Timeouts,
+    error::{Error, Item, PdiError},
+    subdevice_group::Op,
+    MainDevice, SubDeviceGroup, SubDeviceIdentity, SubDeviceRef,
 };
+use serde::{Serialize, Serializer};
 
-/// Maximum number of SubDevices that can be stored. This must be a power of 2 greater than 1.
-const MAX_SUBDEVICES: usize = 16;
-/// Maximum PDU data payload size - set this to the max PDI size or higher.
-const MAX_PDU_DATA: usize = PduStorage::element_size(1100);
-/// Maximum number of EtherCAT frames that can be in flight at any one time.
-const MAX_FRAMES: usize = 16;
-/// Maximum total PDI length.
-const PDI_LEN: usize = 2048;
-
-static PDU_STORAGE: PduStorage<MAX_FRAMES, MAX_PDU_DATA> = PduStorage::new();
+/// The capacities lsecat is built for, as (devices, PDI bytes), smallest
+/// first; --max-devices and --pdi-len pick the smallest that fits. Device
+/// counts must be powers of 2 greater than 1, and PDI sizes no more than
+/// the PDU storage carries.
+const CAPACITIES: [(usize, usize); 3] = [(16, 1024), (128, 8192), (1024, 16384)];
+const _: () = assert!(CAPACITIES[CAPACITIES.len() - 1].1 <= MAX_PDI_LEN);
+/// The header line of --format csv.
+const CSV_HEADER: &str = "address,name,vendor,product,revision,serial,alias,delay,in,out";
+/// How long to wait for every device to reach OP.
+const STATE_TRANSITION_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to cycle the PDI while waiting for OP.
+const CYCLE_TIME: Duration = Duration::from_millis(5);
 
 #[derive(FromArgs)]
 /// List all the devices on the connected EtherCAT network.
@@ -30,6 +53,11 @@ struct Cli {
     #[argh(positional)]
     /// the network interface the EtherCAT bus is connected to
     interface: String,
+    #[argh(option, short = 'd')]
+    /// only list devices matching this: a name or "quoted name", a glob
+    /// like EL3*, @address, #position, or alias:n; can be given more than
+    /// once
+    device: Vec<Selector>,
     #[argh(switch)]
     /// show all available metadata for each device
     meta: bool,
@@ -41,107 +69,723 @@ struct Cli {
     /// show all available data about the device; requires that the
     /// network can enter OP
     long: bool,
+    #[argh(switch)]
+    /// estimate the frames and wire time each process data cycle needs,
+    /// and so the shortest feasible cycle time; requires that the network
+    /// can enter OP
+    budget: bool,
+    #[argh(switch)]
+    /// show which port of which device each device is plugged into,
+    /// indenting devices that hang off a branch rather than the line
+    topology: bool,
+    #[argh(switch)]
+    /// show each device's distributed clock width, system time offset,
+    /// propagation delay, and system time difference
+    dc: bool,
+    #[argh(switch)]
+    /// show each device's mailbox sizes, warning where the SII, the
+    /// SyncManagers, and what the device sends disagree
+    mailbox: bool,
+    #[argh(switch)]
+    /// show each device's error counters, e.g. RX errors and lost links,
+    /// listing only those that aren't zero
+    errors: bool,
+    #[argh(switch)]
+    /// with --errors, reset the error counters of the listed devices
+    /// after reading them
+    clear: bool,
+    #[argh(switch)]
+    /// start with a summary of the bus: how many devices there are, how
+    /// many aren't in the expected state, the PDI size, which device is
+    /// the reference clock, and how long the scan took
+    summary: bool,
+    #[argh(option, default = "Format::Plain")]
+    /// how to print the devices: plain, one per line; csv, with a header
+    /// and the columns address, name, vendor, product, revision, serial,
+    /// alias, delay, in, and out, all in decimal; or json, as an array
+    format: Format,
+    #[argh(switch)]
+    /// the same as --format json
+    json: bool,
+    #[argh(switch)]
+    /// turn off NIC offloads and interrupt coalescing while running,
+    /// restoring them on exit; see `ecat doctor`
+    tune_nic: bool,
+    #[argh(switch)]
+    /// print how long each phase of bus startup and teardown took to
+    /// stderr
+    timing: bool,
+    #[argh(switch)]
+    /// with --pdo or --long, still list the network when some devices
+    /// fail to reach OP
+    keep_going: bool,
+    #[argh(option, default = "128")]
+    /// the most devices the bus may have, up to 1024; smaller buses use
+    /// less memory
+    max_devices: usize,
+    #[argh(option, default = "8192")]
+    /// the most bytes of process data the bus may have, up to 16384
+    pdi_len: usize,
+    #[argh(option)]
+    /// compare the bus against the devices expected in this TOML file
+    /// instead of listing it, exiting non-zero if they differ
+    check: Option<PathBuf>,
+    #[argh(option)]
+    /// write the devices on the bus to this TOML file in the format --check
+    /// reads, including PDO sizes with --pdo
+    snapshot: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Plain,
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("{s:?} isn't one of plain, csv, or json")),
+        }
+    }
+}
+
+impl Cli {
+    fn format(&self) -> Format {
+        if self.json {
+            Format::Json
+        } else {
+            self.format
+        }
+    }
+
+    /// Whether to reset the error counters of a device, which only
+    /// happens to those the report is about.
+    fn clears(&self, selected: bool) -> bool {
+        self.errors && self.clear && selected
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() {
     let cli: Cli = argh::from_env();
+    let capacity = CAPACITIES
+        .iter()
+        .position(|&(devices, pdi_len)| cli.max_devices <= devices && cli.pdi_len <= pdi_len);
+    let run = async {
+        match capacity {
+            Some(0) => run::<{ CAPACITIES[0].0 }, { CAPACITIES[0].1 }>(cli).await,
+            Some(1) => run::<{ CAPACITIES[1].0 }, { CAPACITIES[1].1 }>(cli).await,
+            Some(2) => run::<{ CAPACITIES[2].0 }, { CAPACITIES[2].1 }>(cli).await,
+            _ => {
+                let (devices, pdi_len) = CAPACITIES[CAPACITIES.len() - 1];
+                println!("lsecat handles at most {devices} devices and {pdi_len} PDI bytes");
+                std::process::exit(1);
+            }
+        }
+    };
+    let Some(result) = shutdown::run_until_shutdown(run).await else {
+        std::process::exit(130);
+    };
+    if let Err(err) = result {
+        println!("{err}");
+        std::process::exit(1);
+    }
+}
 
-    let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+async fn run<const MAX_SUBDEVICES: usize, const PDI_LEN: usize>(
+    cli: Cli,
+) -> Result<(), ecat_utils::Error> {
+    if cli.clear && !cli.errors {
+        println!("--clear only goes with --errors");
+        std::process::exit(1);
+    }
+    let expected = cli
+        .check
+        .as_deref()
+        .map(|path| Network::load(path).context(format!("failed to load {}", path.display())))
+        .transpose()?;
+    let nic_tuning = if cli.tune_nic {
+        match NicTuning::apply(&cli.interface) {
+            Ok(tuning) => Some(tuning),
+            Err(err) => {
+                println!("failed to tune {}: {err}", cli.interface);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
 
-    let maindevice = Arc::new(MainDevice::new(
-        pdu_loop,
-        Timeouts {
-            wait_loop_delay: Duration::from_millis(2),
-            mailbox_response: Duration::from_millis(1000),
-            ..Default::default()
-        },
-        MainDeviceConfig::default(),
-    ));
+    let mut timings = Timings::default();
 
-    match tx_rx_task(&cli.interface, tx, rx) {
-        Ok(task) => tokio::spawn(task),
+    let start = Instant::now();
+    let bus = match Bus::builder()
+        .state_transition_timeout(STATE_TRANSITION_TIMEOUT)
+        .open(&cli.interface)
+    {
+        Ok(bus) => bus,
         Err(err) => {
             println!("{err}");
+            drop(nic_tuning);
             std::process::exit(1);
         }
     };
+    let maindevice = bus.maindevice();
+    timings.record("open interface", start);
 
-    let Ok(group) = maindevice
-        .init_single_group::<MAX_SUBDEVICES, PDI_LEN>(ethercat_now)
-        .await
-    else {
-        println!("failed to init; EtherCAT bus could be on a different interface, disconnected, or timing out");
-        std::process::exit(1);
+    let start = Instant::now();
+    let group = match bus.init::<MAX_SUBDEVICES, PDI_LEN>().await {
+        Ok(group) => group,
+        Err(err) => {
+            println!(
+                "failed to init; {}",
+                init_error_reason::<MAX_SUBDEVICES, PDI_LEN>(err)
+            );
+            drop(nic_tuning);
+            std::process::exit(1);
+        }
     };
+    let scan_time = start.elapsed();
+    timings.record("init (enumeration, EEPROM, DC, SM/FMMU config)", start);
 
     let mut subdevice_datas: Vec<SubdeviceData> = group
-        .iter(&maindevice)
+        .iter(maindevice)
         .map(|subdevice| SubdeviceData::new(subdevice.name(), subdevice.configured_address()))
         .collect();
+    let selected: Vec<bool> = group
+        .iter(maindevice)
+        .enumerate()
+        .map(|(i, subdevice)| {
+            cli.device.is_empty()
+                || cli
+                    .device
+                    .iter()
+                    .any(|selector| selector.matches(i, &subdevice))
+        })
+        .collect();
 
-    if cli.meta || cli.long {
-        for (i, subdevice) in group.iter(&maindevice).enumerate() {
+    if cli.meta
+        || cli.long
+        || cli.format() == Format::Csv
+        || cli.check.is_some()
+        || cli.snapshot.is_some()
+    {
+        for (i, subdevice) in group.iter(maindevice).enumerate() {
+            let start = Instant::now();
             subdevice_datas[i].description = Some(
                 subdevice
                     .description()
-                    .await?
+                    .await
+                    .context(format!(
+                        "failed to read the description of {:#06x}",
+                        subdevice.configured_address()
+                    ))?
                     .unwrap_or_default()
                     .to_string(),
             );
             subdevice_datas[i].identity = Some(subdevice.identity());
             subdevice_datas[i].alias_address = Some(subdevice.alias_address());
             subdevice_datas[i].propagation_delay = Some(subdevice.propagation_delay());
+            subdevice_datas[i].al_status = Some(al::read(&subdevice).await.context(format!(
+                "failed to read the AL status of {:#06x}",
+                subdevice.configured_address()
+            ))?);
+            subdevice_datas[i].esc = Some(esc::read(&subdevice).await.context(format!(
+                "failed to read the ESC information of {:#06x}",
+                subdevice.configured_address()
+            ))?);
+            timings.record(
+                format!(
+                    "metadata {:#06x} {}",
+                    subdevice.configured_address(),
+                    subdevice.name()
+                ),
+                start,
+            );
+        }
+
+        // ethercrab exchanges process data with LRW only, with no fallback
+        // to separate LRD and LWR datagrams.
+        for datum in &subdevice_datas {
+            if datum.esc.is_some_and(|esc| !esc.supports_lrw()) {
+                eprintln!(
+                    "warning: {:#06x} {} doesn't support LRW, so its process data won't be exchanged",
+                    datum.address, datum.name
+                );
+            }
         }
     }
 
-    if !(cli.pdo || cli.long) {
-        for datum in subdevice_datas {
-            println!("{datum}");
+    if cli.topology {
+        let mut open_ports = vec![];
+        for subdevice in group.iter(maindevice) {
+            let dl_status = subdevice
+                .register_read(register::DL_STATUS)
+                .await
+                .context(format!(
+                    "failed to read the DL status of {:#06x}",
+                    subdevice.configured_address()
+                ))?;
+            open_ports.push(topology::open_ports(dl_status));
+        }
+        for (i, upstream) in topology::upstreams(&open_ports).into_iter().enumerate() {
+            if let Some(upstream) = upstream {
+                subdevice_datas[i].upstream = Some(UpstreamData {
+                    address: subdevice_datas[upstream.index].address,
+                    port: upstream.port,
+                });
+            }
+        }
+    }
+
+    if cli.dc {
+        for (i, subdevice) in group.iter(maindevice).enumerate() {
+            subdevice_datas[i].dc = Some(dc::read(&subdevice).await.context(format!(
+                "failed to read the DC registers of {:#06x}",
+                subdevice.configured_address()
+            ))?);
+        }
+    }
+
+    if cli.mailbox {
+        for (i, subdevice) in group.iter(maindevice).enumerate() {
+            let sizes = mailbox::read(&subdevice, &bus.quirks(&subdevice))
+                .await
+                .context(format!(
+                    "failed to read the mailbox sizes of {:#06x}",
+                    subdevice.configured_address()
+                ))?;
+            for problem in sizes.iter().flat_map(MailboxSizes::problems) {
+                eprintln!(
+                    "warning: {:#06x} {}: {problem}",
+                    subdevice.configured_address(),
+                    subdevice.name()
+                );
+            }
+            subdevice_datas[i].mailbox = Some(sizes);
+        }
+    }
+
+    if cli.errors {
+        for (i, subdevice) in group.iter(maindevice).enumerate() {
+            subdevice_datas[i].errors = Some(counters::read(&subdevice).await.context(format!(
+                "failed to read the error counters of {:#06x}",
+                subdevice.configured_address()
+            ))?);
+            if cli.clears(selected[i]) {
+                counters::clear(&subdevice).await.context(format!(
+                    "failed to clear the error counters of {:#06x}",
+                    subdevice.configured_address()
+                ))?;
+            }
+        }
+    }
+
+    if !(cli.pdo || cli.long || cli.budget) {
+        if cli.summary {
+            let summary = Summary::read(group.iter(maindevice), al::PRE_OP, None, scan_time)
+                .await
+                .context("failed to summarize the bus")?;
+            print_summary(&summary, &cli);
+        }
+        let matches = report(&subdevice_datas, &selected, &cli, expected.as_ref())?;
+        let start = Instant::now();
+        bus.close(group).await?;
+        timings.record("PRE-OP -> INIT", start);
+        if cli.timing {
+            eprint!("{timings}");
+        }
+        if !matches {
+            drop(nic_tuning);
+            std::process::exit(1);
         }
-        group.into_init(&maindevice).await?;
         return Ok(());
     }
 
-    let group = group.into_op(&maindevice).await?;
+    let start = Instant::now();
+    let group = match group.into_inner().into_safe_op(maindevice).await {
+        Ok(group) => group,
+        Err(err) => {
+            // The failed transition took the group with it, so go by address.
+            for data in &subdevice_datas {
+                match al::read_at(maindevice, data.address).await {
+                    Ok(status) if status.state == al::SAFE_OP && !status.error => {}
+                    Ok(status) => println!(
+                        "{:#06x} {} didn't reach SAFE-OP: in {status}",
+                        data.address, data.name
+                    ),
+                    Err(err) => println!(
+                        "{:#06x} {} didn't reach SAFE-OP: {err}",
+                        data.address, data.name
+                    ),
+                }
+            }
+            if let Err(err) = shutdown::request_init(maindevice).await {
+                eprintln!("failed to ask every device to go to INIT: {err}");
+            }
+            return Err(ecat_utils::Error::Other(format!(
+                "failed to enter SAFE-OP; {}",
+                init_error_reason::<MAX_SUBDEVICES, PDI_LEN>(err)
+            )));
+        }
+    };
+    let group = group
+        .request_into_op(maindevice)
+        .await
+        .context("failed to request OP")?;
+    let group = TeardownGuard::new(group, maindevice.clone());
+    let timeout = group
+        .iter(maindevice)
+        .map(|subdevice| {
+            bus.quirks(&subdevice)
+                .state_timeout(STATE_TRANSITION_TIMEOUT)
+        })
+        .max()
+        .unwrap_or(STATE_TRANSITION_TIMEOUT);
+    let stuck = wait_for_op(&group, maindevice, timeout)
+        .await
+        .context("failed while waiting for OP")?;
+    timings.record("PRE-OP -> OP", start);
+    for report in &stuck {
+        println!("{report}");
+    }
+    if !stuck.is_empty() && !cli.keep_going {
+        drop(group);
+        drop(nic_tuning);
+        std::process::exit(1);
+    }
 
-    for (i, subdevice) in group.iter(&maindevice).enumerate() {
+    for (i, subdevice) in group.iter(maindevice).enumerate() {
         let io = subdevice.io_raw();
         subdevice_datas[i].input_len = Some(io.inputs().len());
         subdevice_datas[i].output_len = Some(io.outputs().len());
     }
+    let pdi_len = subdevice_datas
+        .iter()
+        .map(|datum| datum.input_len.unwrap_or(0) + datum.output_len.unwrap_or(0))
+        .sum();
 
-    for datum in subdevice_datas {
-        println!("{datum}");
+    if cli.summary {
+        let summary = Summary::read(group.iter(maindevice), al::OP, Some(pdi_len), scan_time)
+            .await
+            .context("failed to summarize the bus")?;
+        print_summary(&summary, &cli);
+    }
+    let matches = report(&subdevice_datas, &selected, &cli, expected.as_ref())?;
+    if cli.budget {
+        let max_propagation_delay = group
+            .iter(maindevice)
+            .map(|subdevice| subdevice.propagation_delay())
+            .max()
+            .unwrap_or(0);
+        let budget = Budget::estimate(pdi_len, MAX_PDU_PAYLOAD, max_propagation_delay);
+        if cli.format() != Format::Plain {
+            eprintln!("{budget}");
+        } else {
+            println!("{budget}");
+        }
+        match host::mtu(&cli.interface) {
+            Ok(mtu) if budget.largest_payload > mtu as usize => eprintln!(
+                "warning: frames need {}B of payload, more than the {mtu}B MTU of {}",
+                budget.largest_payload, cli.interface
+            ),
+            Ok(_) => {}
+            Err(err) => eprintln!("warning: couldn't read the MTU of {}: {err}", cli.interface),
+        }
+        if budget.frames > 1 {
+            eprintln!(
+                "warning: the process data is split over {} frames a cycle, each adding its own latency",
+                budget.frames
+            );
+        }
     }
 
-    let _group = close_ethercat(group, maindevice).await?;
+    let start = Instant::now();
+    bus.close(group).await?;
+    timings.record("OP -> INIT", start);
+    if cli.timing {
+        eprint!("{timings}");
+    }
+    if !matches {
+        drop(nic_tuning);
+        std::process::exit(1);
+    }
 
     Ok(())
 }
 
-async fn close_ethercat(
-    group: SubDeviceGroup<{ MAX_SUBDEVICES }, { PDI_LEN }, Op>,
-    maindevice: Arc<MainDevice<'_>>,
-) -> Result<SubDeviceGroup<{ MAX_SUBDEVICES }, { PDI_LEN }, Init>, Error> {
-    let group = group.into_safe_op(&maindevice).await?;
+/// Cycle the PDI until every device reaches OP, returning a description
+/// of each device that didn't get there within `timeout`.
+async fn wait_for_op<const MAX_SUBDEVICES: usize, const PDI_LEN: usize>(
+    group: &SubDeviceGroup<MAX_SUBDEVICES, PDI_LEN, Op>,
+    maindevice: &MainDevice<'_>,
+    timeout: Duration,
+) -> Result<Vec<String>, ecat_utils::Error> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        group.tx_rx(maindevice).await?;
+        if group.all_op(maindevice).await? {
+            return Ok(vec![]);
+        }
+        tokio::time::sleep(CYCLE_TIME).await;
+    }
 
-    let group = group.into_pre_op(&maindevice).await?;
+    let mut stuck = vec![];
+    for subdevice in group.iter(maindevice) {
+        let status = al::read(&subdevice).await?;
+        if status.state == al::OP {
+            continue;
+        }
+        let control: u16 = subdevice.register_read(register::AL_CONTROL).await?;
+        stuck.push(format!(
+            "{:#06x} {} didn't reach OP: in {status} (AL control {control:#06x})",
+            subdevice.configured_address(),
+            subdevice.name(),
+        ));
+    }
+    Ok(stuck)
+}
 
-    group.into_init(&maindevice).await
+fn init_error_reason<const MAX_SUBDEVICES: usize, const PDI_LEN: usize>(err: Error) -> String {
+    match err {
+        Error::Capacity(Item::SubDevice) => {
+            format!("there are more than {MAX_SUBDEVICES} devices on the bus; raise --max-devices")
+        }
+        Error::Pdi(PdiError::TooLong { desired_length, .. }) => format!(
+            "the devices have {desired_length} bytes of process data, more than PDI_LEN ({PDI_LEN} bytes); raise --pdi-len"
+        ),
+        Error::Capacity(Item::Pdu) => {
+            format!("a PDU didn't fit in {MAX_PDU_DATA} bytes of PDU storage")
+        }
+        Error::SubDevice(code) => format!("a device refused the state change: {code}"),
+        Error::Timeout => "EtherCAT bus could be on a different interface, disconnected, or timing out".into(),
+        err => format!(
+            "{err} (limits are {MAX_SUBDEVICES} devices, {PDI_LEN} PDI bytes, and {MAX_PDU_DATA} PDU bytes; see --max-devices and --pdi-len)"
+        ),
+    }
+}
+
+/// The bus at a glance, for --summary.
+struct Summary {
+    devices: usize,
+    /// The state every device should be in by now.
+    state: u8,
+    /// How many devices aren't in `state`, or are flagging an error.
+    wrong_state: usize,
+    /// Only known once the devices have been through SAFE-OP.
+    pdi_len: Option<usize>,
+    /// The address and name of the first device with a DC.
+    reference_clock: Option<(u16, String)>,
+    /// How long the bus took to enumerate and configure.
+    scan_time: Duration,
+}
+
+impl Summary {
+    async fn read<'a, S: 'a>(
+        subdevices: impl Iterator<Item = SubDeviceRef<'a, S>>,
+        state: u8,
+        pdi_len: Option<usize>,
+        scan_time: Duration,
+    ) -> Result<Self, ecat_utils::Error> {
+        let mut summary = Self {
+            devices: 0,
+            state,
+            wrong_state: 0,
+            pdi_len,
+            reference_clock: None,
+            scan_time,
+        };
+        for subdevice in subdevices {
+            summary.devices += 1;
+            let status = al::read(&subdevice).await?;
+            if status.state != state || status.error {
+                summary.wrong_state += 1;
+            }
+            if summary.reference_clock.is_none() {
+                let features = subdevice.register_read(register::ESC_FEATURES).await?;
+                if dc::has_dc(features) {
+                    summary.reference_clock =
+                        Some((subdevice.configured_address(), subdevice.name().to_string()));
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "devices:{} not-{}:{}",
+            self.devices,
+            al::state_name(self.state)
+                .unwrap_or("?")
+                .to_ascii_lowercase(),
+            self.wrong_state
+        )?;
+        if let Some(pdi_len) = self.pdi_len {
+            write!(f, " pdi:{pdi_len}B")?;
+        }
+        match &self.reference_clock {
+            Some((address, name)) => write!(f, " reference-clock:{address:#06x} {name}")?,
+            None => write!(f, " reference-clock:none")?,
+        }
+        write!(f, " scan:{:.3}ms", self.scan_time.as_secs_f64() * 1000.0)
+    }
+}
+
+/// Print the summary ahead of the devices, keeping it out of the way of
+/// output meant for other programs.
+fn print_summary(summary: &Summary, cli: &Cli) {
+    if cli.format() != Format::Plain {
+        eprintln!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+}
+
+/// How long each phase of talking to the bus took.
+#[derive(Default)]
+struct Timings(Vec<(String, Duration)>);
+
+impl Timings {
+    fn record(&mut self, phase: impl Into<String>, start: Instant) {
+        self.0.push((phase.into(), start.elapsed()));
+    }
+}
+
+impl std::fmt::Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (phase, duration) in &self.0 {
+            writeln!(f, "{:>10.3}ms {phase}", duration.as_secs_f64() * 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Print, check, or snapshot the devices, returning false if they don't
+/// match what --check expects.
+fn report(
+    subdevice_datas: &[SubdeviceData],
+    selected: &[bool],
+    cli: &Cli,
+    expected: Option<&Network>,
+) -> Result<bool, ecat_utils::Error> {
+    let actual = || {
+        subdevice_datas
+            .iter()
+            .map(SubdeviceData::to_expected)
+            .collect()
+    };
+    if let Some(path) = &cli.snapshot {
+        let network = Network { devices: actual() };
+        std::fs::write(
+            path,
+            toml::to_string(&network).expect("always serializable"),
+        )
+        .context(format!("failed to write {}", path.display()))?;
+    }
+    let Some(expected) = expected else {
+        if cli.snapshot.is_none() {
+            print_subdevices(&only_selected(subdevice_datas, selected), cli);
+        }
+        return Ok(true);
+    };
+
+    let mismatches = network::compare(&expected.devices, &actual());
+    for mismatch in &mismatches {
+        println!("{mismatch}");
+    }
+    if mismatches.is_empty() {
+        println!(
+            "bus matches the {} expected devices",
+            expected.devices.len()
+        );
+    }
+    Ok(mismatches.is_empty())
+}
+
+fn only_selected<'a>(
+    subdevice_datas: &'a [SubdeviceData],
+    selected: &[bool],
+) -> Vec<&'a SubdeviceData> {
+    subdevice_datas
+        .iter()
+        .zip(selected)
+        .filter_map(|(datum, selected)| selected.then_some(datum))
+        .collect()
+}
+
+fn print_subdevices(subdevice_datas: &[&SubdeviceData], cli: &Cli) {
+    if cli.format() == Format::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(subdevice_datas).expect("always serializable")
+        );
+    } else if cli.format() == Format::Csv {
+        println!("{CSV_HEADER}");
+        for datum in subdevice_datas {
+            println!("{}", datum.csv_row());
+        }
+    } else if cli.topology {
+        // Devices continuing the line on port 1 stay at their upstream's
+        // depth; anything on another port starts a branch.
+        let mut depths = HashMap::new();
+        for datum in subdevice_datas {
+            let depth = match &datum.upstream {
+                // The upstream device may have been filtered out.
+                Some(upstream) => {
+                    depths.get(&upstream.address).copied().unwrap_or(0)
+                        + usize::from(upstream.port != 1)
+                }
+                None => 0,
+            };
+            depths.insert(datum.address, depth);
+            println!("{:indent$}{datum}", "", indent = 2 * depth);
+        }
+    } else {
+        for datum in subdevice_datas {
+            println!("{datum}");
+        }
+    }
 }
 
+#[derive(Serialize)]
 struct SubdeviceData {
     name: String,
     address: u16,
     description: Option<String>,
+    #[serde(serialize_with = "serialize_identity")]
     identity: Option<SubDeviceIdentity>,
     alias_address: Option<u16>,
     propagation_delay: Option<u32>,
+    al_status: Option<AlStatus>,
+    esc: Option<EscInfo>,
+    upstream: Option<UpstreamData>,
+    /// `Some(None)` for a device without a distributed clock.
+    dc: Option<Option<DcStatus>>,
+    /// `Some(None)` for a device without a mailbox.
+    mailbox: Option<Option<MailboxSizes>>,
+    errors: Option<ErrorCounters>,
     input_len: Option<usize>,
     output_len: Option<usize>,
 }
 
+/// The device and port a device is plugged into.
+#[derive(Serialize)]
+struct UpstreamData {
+    address: u16,
+    port: u8,
+}
+
 impl std::fmt::Display for SubdeviceData {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:#06x} {}", self.address, self.name)?;
@@ -157,6 +801,39 @@ impl std::fmt::Display for SubdeviceData {
         if let Some(delay) = self.propagation_delay {
             write!(f, " delay:{}ns", delay)?;
         }
+        if let Some(al_status) = &self.al_status {
+            write!(f, " state:{al_status}")?;
+        }
+        if let Some(esc) = &self.esc {
+            write!(f, " esc:{esc}")?;
+        }
+        if let Some(upstream) = &self.upstream {
+            write!(f, " upstream:{:#06x}/{}", upstream.address, upstream.port)?;
+        }
+        match &self.dc {
+            Some(Some(dc)) => write!(f, " dc:{dc}")?,
+            Some(None) => write!(f, " dc:none")?,
+            None => {}
+        }
+        match &self.mailbox {
+            Some(Some(mailbox)) => write!(f, " mailbox:{mailbox}")?,
+            Some(None) => write!(f, " mailbox:none")?,
+            None => {}
+        }
+        if let Some(errors) = &self.errors {
+            let nonzero: Vec<_> = errors
+                .nonzero()
+                .iter()
+                .map(|(name, count)| format!("{name}={count}"))
+                .collect();
+            if nonzero.is_empty() {
+                write!(f, " errors:none")?;
+            } else if std::io::stdout().is_terminal() {
+                write!(f, " errors:\x1b[1;31m{}\x1b[0m", nonzero.join(","))?;
+            } else {
+                write!(f, " errors:{}", nonzero.join(","))?;
+            }
+        }
         if let Some(i) = self.input_len {
             write!(f, " in:{}B", i)?;
         }
@@ -168,6 +845,43 @@ impl std::fmt::Display for SubdeviceData {
 }
 
 impl SubdeviceData {
+    /// The columns of --format csv, leaving out what wasn't read. Numbers
+    /// are all decimal, so spreadsheets read every column the same way.
+    fn csv_row(&self) -> String {
+        fn column<T>(value: Option<T>, format: impl Fn(T) -> String) -> String {
+            value.map(format).unwrap_or_default()
+        }
+        let identity = self.identity;
+        [
+            self.address.to_string(),
+            csv_escape(&self.name),
+            column(identity, |identity| identity.vendor_id.to_string()),
+            column(identity, |identity| identity.product_id.to_string()),
+            column(identity, |identity| identity.revision.to_string()),
+            column(identity, |identity| identity.serial.to_string()),
+            column(self.alias_address, |alias| alias.to_string()),
+            column(self.propagation_delay, |delay| delay.to_string()),
+            column(self.input_len, |len| len.to_string()),
+            column(self.output_len, |len| len.to_string()),
+        ]
+        .join(",")
+    }
+
+    /// This device as --check and --snapshot describe it. Needs the
+    /// metadata to have been read.
+    fn to_expected(&self) -> network::Device {
+        let identity = self.identity.expect("metadata is read for --check");
+        network::Device {
+            name: Some(self.name.clone()),
+            vendor: identity.vendor_id,
+            product: identity.product_id,
+            revision: Some(identity.revision),
+            alias: self.alias_address,
+            input_len: self.input_len,
+            output_len: self.output_len,
+        }
+    }
+
     fn new(name: &str, address: u16) -> Self {
         Self {
             name: name.into(),
@@ -176,12 +890,27 @@ impl SubdeviceData {
             identity: None,
             alias_address: None,
             propagation_delay: None,
+            al_status: None,
+            esc: None,
+            dc: None,
+            mailbox: None,
+            errors: None,
+            upstream: None,
             input_len: None,
             output_len: None,
         }
     }
 }
 
+/// Quote a CSV field if it needs it.
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.into()
+    }
+}
+
 fn escape(s: &str) -> String {
     if !s.contains([' ', '\t', '\n', '\r']) {
         s.into()
@@ -204,9 +933,87 @@ fn escape(s: &str) -> String {
     }
 }
 
+fn serialize_identity<S: Serializer>(
+    identity: &Option<SubDeviceIdentity>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Identity {
+        vendor: u32,
+        product: u32,
+        revision: u32,
+        serial: u32,
+    }
+
+    identity
+        .map(|identity| Identity {
+            vendor: identity.vendor_id,
+            product: identity.product_id,
+            revision: identity.revision,
+            serial: identity.serial,
+        })
+        .serialize(serializer)
+}
+
 fn fmt_identity(identity: SubDeviceIdentity) -> String {
     format!(
         "vendor:{:#010x} product:{:#010x} rev:{} serial:{}",
         identity.vendor_id, identity.product_id, identity.revision, identity.serial
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::from_args(&["lsecat"], &[&["eth0"], args].concat()).unwrap()
+    }
+
+    #[test]
+    fn csv_row() {
+        let mut datum = SubdeviceData::new("EL3102 \"2Ch. Ana. In\", -10..10V", 0x1001);
+        assert_eq!(
+            datum.csv_row(),
+            "4097,\"EL3102 \"\"2Ch. Ana. In\"\", -10..10V\",,,,,,,,"
+        );
+        datum.identity = Some(SubDeviceIdentity {
+            vendor_id: 0x2,
+            product_id: 0x0c1e3052,
+            revision: 0x00140000,
+            serial: 1234,
+        });
+        datum.alias_address = Some(0x10);
+        datum.propagation_delay = Some(150);
+        datum.input_len = Some(6);
+        datum.output_len = Some(0);
+        let row = datum.csv_row();
+        assert_eq!(
+            row,
+            "4097,\"EL3102 \"\"2Ch. Ana. In\"\", -10..10V\",2,203305042,1310720,1234,16,150,6,0"
+        );
+
+        let plain = SubdeviceData::new("EK1100", 0x1000).csv_row();
+        assert_eq!(plain, "4096,EK1100,,,,,,,,");
+        assert_eq!(plain.split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn csv_escapes() {
+        assert_eq!(csv_escape("EL1008"), "EL1008");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_escape(""), "");
+    }
+
+    #[test]
+    fn clears() {
+        let all = cli(&["--errors", "--clear"]);
+        assert!(all.clears(true));
+        let selecting = cli(&["-d", "#3", "--errors", "--clear"]);
+        assert!(selecting.clears(true));
+        assert!(!selecting.clears(false));
+        assert!(!cli(&["--errors"]).clears(true));
+    }
+}
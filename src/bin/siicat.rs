@@ -0,0 +1,374 @@
+//! Read and decode the SII EEPROM of devices on the EtherCAT network
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use argh::FromArgs;
+use ecat_utils::{
+    error::{Context, Error},
+    esi::{self, EsiFile},
+    runtime::Bus,
+    select::Selector,
+    shutdown,
+    sii::{self, Header, Sii},
+};
+use ethercrab::SubDeviceRef;
+
+/// Maximum number of SubDevices that can be stored. This must be a power of 2 greater than 1.
+const MAX_SUBDEVICES: usize = 128;
+/// Maximum total PDI length.
+const PDI_LEN: usize = 8192;
+
+#[derive(FromArgs)]
+/// Read and decode the SII EEPROM of devices on an EtherCAT network.
+///
+/// Without --dump or --write, this prints the header and the standard
+/// categories (strings, general, FMMU, SyncM, and PDOs) of each device.
+struct Cli {
+    #[argh(positional)]
+    /// the network interface the EtherCAT bus is connected to
+    interface: String,
+    #[argh(positional)]
+    /// the device to read, as a name, glob, @address, #position, or
+    /// alias:n; every device if left out
+    device: Option<Selector>,
+    #[argh(option)]
+    /// write the raw EEPROM contents of the device to this file instead
+    /// of decoding them
+    dump: Option<PathBuf>,
+    #[argh(option)]
+    /// write this image to the device's EEPROM and verify it; either a raw
+    /// .bin or an ESI .xml that includes the EEPROM data
+    write: Option<PathBuf>,
+    #[argh(switch)]
+    /// write even if the image is for a different vendor or product
+    force: bool,
+    #[argh(switch)]
+    /// with --write, write every word of the image, not only those that
+    /// differ from what's on the EEPROM
+    full: bool,
+    #[argh(option, default = "1000")]
+    /// how many milliseconds to wait for a device's own processor to let
+    /// go of its EEPROM
+    access_timeout_ms: u64,
+    #[argh(switch)]
+    /// take the EEPROM from a device's own processor even if it hasn't
+    /// let go of it, which may upset what it was doing
+    force_access: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli: Cli = argh::from_env();
+    let Some(result) = shutdown::run_until_shutdown(run(cli)).await else {
+        std::process::exit(130);
+    };
+    if let Err(err) = result {
+        println!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    if cli.dump.is_some() && cli.write.is_some() {
+        return Err(Error::Other(
+            "--dump and --write can't be used together".into(),
+        ));
+    }
+    if (cli.dump.is_some() || cli.write.is_some()) && cli.device.is_none() {
+        return Err(Error::Other(
+            "--dump and --write need a single device".into(),
+        ));
+    }
+
+    let bus = Bus::open(&cli.interface)?;
+    let maindevice = bus.maindevice();
+    let group = bus
+        .init::<MAX_SUBDEVICES, PDI_LEN>()
+        .await
+        .context("failed to init")?;
+
+    let subdevices: Vec<_> = match &cli.device {
+        Some(selector) => vec![selector.resolve(group.iter(maindevice))?],
+        None => group.iter(maindevice).collect(),
+    };
+
+    let count = subdevices.len();
+    let mut failed = 0;
+    for subdevice in subdevices {
+        let config = match sii::acquire(
+            &subdevice,
+            Duration::from_millis(cli.access_timeout_ms),
+            cli.force_access,
+        )
+        .await
+        {
+            Ok(config) => config,
+            Err(err) => {
+                println!(
+                    "{:#06x} {} failed to get the EEPROM: {err}; try a longer --access-timeout-ms or --force-access",
+                    subdevice.configured_address(),
+                    subdevice.name()
+                );
+                failed += 1;
+                continue;
+            }
+        };
+        let mut ok = process(&subdevice, &cli).await;
+        if let Err(err) = sii::release(&subdevice, config).await {
+            println!(
+                "{:#06x} {} failed to hand the EEPROM back: {err}",
+                subdevice.configured_address(),
+                subdevice.name()
+            );
+            ok = false;
+        }
+        if !ok {
+            failed += 1;
+        }
+    }
+
+    bus.close(group).await?;
+
+    if failed > 0 {
+        return Err(Error::Other(format!("{failed} of {count} devices failed")));
+    }
+    Ok(())
+}
+
+/// Flash, dump, or print the EEPROM of one device, returning false if
+/// that failed.
+async fn process<S>(subdevice: &SubDeviceRef<'_, S>, cli: &Cli) -> bool {
+    if let Some(path) = &cli.write {
+        if let Err(err) = flash(subdevice, path, cli.force, cli.full).await {
+            println!(
+                "{:#06x} {} {err}",
+                subdevice.configured_address(),
+                subdevice.name()
+            );
+            return false;
+        }
+        return true;
+    }
+
+    let image = match sii::read_image(subdevice).await {
+        Ok(image) => image,
+        Err(err) => {
+            println!(
+                "{:#06x} {} failed to read EEPROM: {err}",
+                subdevice.configured_address(),
+                subdevice.name()
+            );
+            return false;
+        }
+    };
+
+    if let Some(path) = &cli.dump {
+        if let Err(err) = std::fs::write(path, &image) {
+            println!("failed to write {}: {err}", path.display());
+            return false;
+        }
+        return true;
+    }
+
+    match Sii::parse(&image) {
+        Ok(sii) => {
+            print!(
+                "{}",
+                fmt_sii(subdevice.configured_address(), subdevice.name(), &sii)
+            );
+            true
+        }
+        Err(err) => {
+            println!(
+                "{:#06x} {} {err}",
+                subdevice.configured_address(),
+                subdevice.name()
+            );
+            false
+        }
+    }
+}
+
+/// Load the image to write to `subdevice` from a raw dump or an ESI file.
+fn load_image<S>(path: &Path, subdevice: &SubDeviceRef<'_, S>) -> Result<Vec<u8>, String> {
+    let is_esi = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xml"));
+    if !is_esi {
+        return std::fs::read(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()));
+    }
+
+    let file = EsiFile::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    // A blank EEPROM has no identity to match on, so fall back to the only
+    // device in the file.
+    let device = esi::find(std::slice::from_ref(&file), &subdevice.identity())
+        .or(match file.devices.as_slice() {
+            [device] => Some(device),
+            _ => None,
+        })
+        .ok_or_else(|| format!("{} has no device matching this one", path.display()))?;
+    device.eeprom.clone().ok_or_else(|| {
+        format!(
+            "{} has no EEPROM data for {}",
+            path.display(),
+            device.type_name
+        )
+    })
+}
+
+/// Write an image to the EEPROM, then read it back to check it took.
+/// Unless `full`, only the words that differ are written, which is much
+/// quicker and saves wear when reflashing a mostly unchanged image.
+async fn flash<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    path: &Path,
+    force: bool,
+    full: bool,
+) -> Result<(), String> {
+    let image = load_image(path, subdevice)?;
+    let header = Header::parse(&image).map_err(|err| err.to_string())?;
+    if !force {
+        if !header.checksum_ok {
+            return Err("image has a bad header checksum; use --force to write it anyway".into());
+        }
+        let current = subdevice.identity();
+        if (current.vendor_id, current.product_id)
+            != (header.identity.vendor_id, header.identity.product_id)
+        {
+            return Err(format!(
+                "image is for vendor:{:#010x} product:{:#010x}, but the device is vendor:{:#010x} product:{:#010x}; use --force to write it anyway",
+                header.identity.vendor_id,
+                header.identity.product_id,
+                current.vendor_id,
+                current.product_id
+            ));
+        }
+    }
+
+    let current = if full {
+        vec![]
+    } else {
+        sii::read(subdevice, 0, image.len())
+            .await
+            .map_err(|err| format!("failed to read EEPROM: {err}"))?
+    };
+    let written = sii::write_changed(subdevice, &current, &image)
+        .await
+        .map_err(|err| format!("failed to write EEPROM: {err}"))?;
+    let readback = sii::read(subdevice, 0, image.len())
+        .await
+        .map_err(|err| format!("failed to read EEPROM back: {err}"))?;
+    if let Some(offset) = readback.iter().zip(&image).position(|(a, b)| a != b) {
+        return Err(format!(
+            "verify failed: EEPROM differs from the image at word {:#06x}",
+            offset / 2
+        ));
+    }
+    println!(
+        "{:#06x} {} wrote {written} of {} words and verified {} bytes",
+        subdevice.configured_address(),
+        subdevice.name(),
+        image.len() / 2,
+        image.len()
+    );
+    Ok(())
+}
+
+fn fmt_sii(address: u16, name: &str, sii: &Sii) -> String {
+    let header = &sii.header;
+    let identity = header.identity;
+    let mut out = String::new();
+    let _ = writeln!(out, "{address:#06x} {name}");
+    let _ = writeln!(
+        out,
+        "  vendor:{:#010x} product:{:#010x} rev:{:#010x} serial:{} alias:{:#06x}",
+        identity.vendor_id, identity.product_id, identity.revision, identity.serial, header.alias
+    );
+    let _ = writeln!(
+        out,
+        "  eeprom:{}B version:{} checksum:{} pdi-control:{:#06x} pdi-config:{:#06x}",
+        header.size,
+        header.version,
+        if header.checksum_ok { "ok" } else { "bad" },
+        header.pdi_control,
+        header.pdi_config
+    );
+    for (kind, mailbox) in [
+        ("mailbox", header.standard_mailbox),
+        ("bootstrap-mailbox", header.bootstrap_mailbox),
+    ] {
+        let _ = writeln!(
+            out,
+            "  {kind}: out:{:#06x}+{} in:{:#06x}+{}",
+            mailbox.receive_offset, mailbox.receive_size, mailbox.send_offset, mailbox.send_size
+        );
+    }
+    let _ = writeln!(out, "  protocols:{}", header.protocol_names().join(","));
+
+    if let Some(general) = &sii.general {
+        let string = |s: &Option<String>| s.as_deref().unwrap_or("-").to_string();
+        let _ = writeln!(
+            out,
+            "  general: group:{} order:{} name:{} image:{}",
+            string(&general.group),
+            string(&general.order),
+            string(&general.name),
+            string(&general.image)
+        );
+        let _ = writeln!(
+            out,
+            "  general: coe:{:#04x} foe:{:#04x} eoe:{:#04x} flags:{:#04x} ebus-current:{}mA ports:{:#06x}",
+            general.coe_details,
+            general.foe_details,
+            general.eoe_details,
+            general.flags,
+            general.ebus_current,
+            general.physical_ports
+        );
+    }
+    for (i, fmmu) in sii.fmmus.iter().enumerate() {
+        let _ = writeln!(out, "  fmmu{i}: {fmmu}");
+    }
+    for (i, sm) in sii.sync_managers.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "  sm{i}: {} start:{:#06x} len:{} control:{:#04x} enable:{:#04x}",
+            sm.kind_name(),
+            sm.start_address,
+            sm.length,
+            sm.control,
+            sm.enable
+        );
+    }
+    for (direction, pdos) in [("txpdo", &sii.tx_pdos), ("rxpdo", &sii.rx_pdos)] {
+        for pdo in pdos {
+            let _ = writeln!(
+                out,
+                "  {direction} {:#06x} sm:{} {}",
+                pdo.index,
+                pdo.sync_manager,
+                pdo.name.as_deref().unwrap_or("")
+            );
+            for entry in &pdo.entries {
+                let _ = writeln!(
+                    out,
+                    "    {:#06x}:{:02x} {}bit type:{:#04x} {}",
+                    entry.index,
+                    entry.sub_index,
+                    entry.bit_len,
+                    entry.data_type,
+                    entry.name.as_deref().unwrap_or("")
+                );
+            }
+        }
+    }
+    for (kind, data) in &sii.other {
+        let _ = writeln!(out, "  category {kind}: {}B", data.len());
+    }
+    out
+}
@@ -0,0 +1,120 @@
+//! Estimate what one process data cycle costs on the wire.
+
+use std::time::Duration;
+
+/// Bytes on the wire around each frame's EtherCAT payload: preamble and
+/// start of frame, Ethernet header, FCS, and the inter-frame gap.
+const FRAME_OVERHEAD: usize = 8 + 14 + 4 + 12;
+/// The EtherCAT header at the start of each frame's payload.
+const ECAT_HEADER: usize = 2;
+/// Each datagram's header and working counter.
+const DATAGRAM_OVERHEAD: usize = 10 + 2;
+/// Frames are padded up to this much payload.
+const MIN_PAYLOAD: usize = 46;
+/// At 100 Mbit/s.
+const NS_PER_BYTE: u64 = 80;
+
+/// The frames and time one cycle of process data needs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub pdi_len: usize,
+    /// One LRW datagram per frame, each carrying up to the PDU size.
+    pub frames: usize,
+    pub wire_bytes: usize,
+    /// The Ethernet payload of the largest frame, which has to fit in the
+    /// interface's MTU.
+    pub largest_payload: usize,
+    /// How long the frames take to send back to back.
+    pub wire_time: Duration,
+    /// How long the last frame takes to come back after it's sent.
+    pub loop_delay: Duration,
+}
+
+impl Budget {
+    /// Estimate the cost of exchanging `pdi_len` bytes in datagrams of at
+    /// most `max_datagram_data` bytes, with the furthest device
+    /// `max_propagation_delay` ns away.
+    ///
+    /// The frame has to reach the end of the bus and come back, so the
+    /// loop is taken as twice the largest propagation delay.
+    pub fn estimate(pdi_len: usize, max_datagram_data: usize, max_propagation_delay: u32) -> Self {
+        let frames = pdi_len.div_ceil(max_datagram_data);
+        let mut wire_bytes = 0;
+        let mut largest_payload = 0;
+        let mut remaining = pdi_len;
+        for _ in 0..frames {
+            let data = remaining.min(max_datagram_data);
+            remaining -= data;
+            let payload = (ECAT_HEADER + DATAGRAM_OVERHEAD + data).max(MIN_PAYLOAD);
+            wire_bytes += FRAME_OVERHEAD + payload;
+            largest_payload = largest_payload.max(payload);
+        }
+        Self {
+            pdi_len,
+            frames,
+            wire_bytes,
+            largest_payload,
+            wire_time: Duration::from_nanos(wire_bytes as u64 * NS_PER_BYTE),
+            loop_delay: Duration::from_nanos(2 * u64::from(max_propagation_delay)),
+        }
+    }
+
+    /// The shortest cycle the bus could keep up with, ignoring the host.
+    pub fn min_cycle_time(&self) -> Duration {
+        self.wire_time + self.loop_delay
+    }
+}
+
+impl std::fmt::Display for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "pdi:{}B frames:{} wire:{}B/{:.1}us loop:{:.1}us min-cycle:{:.1}us",
+            self.pdi_len,
+            self.frames,
+            self.wire_bytes,
+            self.wire_time.as_secs_f64() * 1e6,
+            self.loop_delay.as_secs_f64() * 1e6,
+            self.min_cycle_time().as_secs_f64() * 1e6
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate() {
+        // (PDI bytes, max datagram data, propagation delay ns) to
+        // (frames, wire bytes, largest payload, wire time ns, loop ns).
+        for (args, expected) in [
+            ((0, 1100, 0), (0, 0, 0, 0, 0)),
+            // Padded up to the minimum frame.
+            ((10, 1100, 500), (1, 84, 46, 6720, 1000)),
+            ((1100, 1100, 0), (1, 1152, 1114, 92_160, 0)),
+            // 1100 then 900 bytes.
+            ((2000, 1100, 250), (2, 2104, 1114, 168_320, 500)),
+        ] {
+            let (pdi_len, max_datagram_data, max_propagation_delay) = args;
+            let budget = Budget::estimate(pdi_len, max_datagram_data, max_propagation_delay);
+            let (frames, wire_bytes, largest_payload, wire_time, loop_delay) = expected;
+            assert_eq!(
+                budget,
+                Budget {
+                    pdi_len,
+                    frames,
+                    wire_bytes,
+                    largest_payload,
+                    wire_time: Duration::from_nanos(wire_time),
+                    loop_delay: Duration::from_nanos(loop_delay),
+                },
+                "{args:?}"
+            );
+            assert_eq!(
+                budget.min_cycle_time(),
+                Duration::from_nanos(wire_time + loop_delay)
+            );
+        }
+    }
+}
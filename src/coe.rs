@@ -0,0 +1,197 @@
+//! CANopen over EtherCAT (CoE) details shared between the tools.
+
+use ethercrab::{SubDeviceRef, SubIndex};
+
+use crate::{
+    esi::{Entry, Object},
+    Error,
+};
+
+/// The RxPDO assignment of SyncManager 2, i.e. the outputs.
+pub const RX_PDO_ASSIGN: u16 = 0x1c12;
+/// The TxPDO assignment of SyncManager 3, i.e. the inputs.
+pub const TX_PDO_ASSIGN: u16 = 0x1c13;
+/// The most a complete access read can return.
+pub const COMPLETE_ACCESS_MAX: usize = 1024;
+
+/// What an SDO abort code means, per ETG.1000.6.
+pub fn abort_description(code: u32) -> Option<&'static str> {
+    Some(match code {
+        0x0503_0000 => "toggle bit not changed",
+        0x0504_0000 => "SDO protocol timeout",
+        0x0504_0001 => "client/server command specifier not valid or unknown",
+        0x0504_0005 => "out of memory",
+        0x0601_0000 => "unsupported access to an object",
+        0x0601_0001 => "attempt to read a write-only object",
+        0x0601_0002 => "attempt to write a read-only object",
+        0x0601_0003 => "sub-index can't be written, sub-index 0 must be 0 for write access",
+        0x0601_0004 => "complete access isn't supported for variable length objects",
+        0x0601_0005 => "object length exceeds mailbox size",
+        0x0601_0006 => "object is mapped to an RxPDO, SDO download blocked",
+        0x0602_0000 => "object does not exist in the object dictionary",
+        0x0604_0041 => "object can't be mapped into the PDO",
+        0x0604_0042 => "mapped objects would exceed the PDO length",
+        0x0604_0043 => "general parameter incompatibility",
+        0x0604_0047 => "general internal incompatibility in the device",
+        0x0606_0000 => "access failed due to a hardware error",
+        0x0607_0010 => "data type does not match, length of service parameter does not match",
+        0x0607_0012 => "data type does not match, length of service parameter too high",
+        0x0607_0013 => "data type does not match, length of service parameter too low",
+        0x0609_0011 => "sub-index does not exist",
+        0x0609_0030 => "value range of parameter exceeded",
+        0x0609_0031 => "value of parameter written too high",
+        0x0609_0032 => "value of parameter written too low",
+        0x0609_0036 => "maximum value is less than minimum value",
+        0x0800_0000 => "general error",
+        0x0800_0020 => "data can't be transferred or stored to the application",
+        0x0800_0021 => "data can't be transferred or stored to the application because of local control",
+        0x0800_0022 => "data can't be transferred or stored to the application because of the present device state",
+        0x0800_0023 => "object dictionary dynamic generation failed or no object dictionary is present",
+        _ => return None,
+    })
+}
+
+/// One object mapped into a PDO. Gaps in the mapping have index 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mapped {
+    pub pdo: u16,
+    pub index: u16,
+    pub sub_index: u8,
+    pub bits: u8,
+}
+
+/// The objects mapped by the PDOs assigned in `assign`, one of
+/// [`RX_PDO_ASSIGN`] or [`TX_PDO_ASSIGN`], in process data order.
+pub async fn assigned_entries<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    assign: u16,
+) -> Result<Vec<Mapped>, Error> {
+    let mut mapped = vec![];
+    let pdos: u8 = subdevice.sdo_read(assign, 0).await?;
+    for i in 1..=pdos {
+        let pdo: u16 = subdevice.sdo_read(assign, i).await?;
+        let entries: u8 = subdevice.sdo_read(pdo, 0).await?;
+        for j in 1..=entries {
+            // index << 16 | sub-index << 8 | bit length
+            let mapping: u32 = subdevice.sdo_read(pdo, j).await?;
+            mapped.push(Mapped {
+                pdo,
+                index: (mapping >> 16) as u16,
+                sub_index: (mapping >> 8) as u8,
+                bits: mapping as u8,
+            });
+        }
+    }
+    Ok(mapped)
+}
+
+/// The total bit length of the PDOs assigned in `assign`, one of
+/// [`RX_PDO_ASSIGN`] or [`TX_PDO_ASSIGN`], per their mappings.
+pub async fn assigned_bits<S>(subdevice: &SubDeviceRef<'_, S>, assign: u16) -> Result<u32, Error> {
+    let mapped = assigned_entries(subdevice, assign).await?;
+    Ok(mapped.iter().map(|entry| u32::from(entry.bits)).sum())
+}
+
+/// Read a whole object in one go with complete access, starting at
+/// sub-index 0.
+pub async fn read_complete<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    index: u16,
+) -> Result<Vec<u8>, Error> {
+    let data: heapless::Vec<u8, COMPLETE_ACCESS_MAX> =
+        subdevice.sdo_read(index, SubIndex::Complete).await?;
+    Ok(data.to_vec())
+}
+
+/// Split the result of [`read_complete`] into the value of each entry of
+/// `object`, little endian and right aligned, leaving out entries past the
+/// end of `data`.
+pub fn split<'a>(object: &'a Object, data: &[u8]) -> Vec<(&'a Entry, Vec<u8>)> {
+    object
+        .entries
+        .iter()
+        .take_while(|entry| (entry.bit_offset + entry.bit_size) as usize <= data.len() * 8)
+        .map(|entry| (entry, bits(data, entry.bit_offset, entry.bit_size)))
+        .collect()
+}
+
+/// `len` bits of `data` from bit `offset` on, shifted down to start at bit
+/// 0 of the first byte.
+fn bits(data: &[u8], offset: u32, len: u32) -> Vec<u8> {
+    if offset.is_multiple_of(8) && len.is_multiple_of(8) {
+        let start = offset as usize / 8;
+        return data[start..start + len as usize / 8].to_vec();
+    }
+    let mut value = vec![0; (len as usize).div_ceil(8)];
+    for i in 0..len {
+        let bit = (offset + i) as usize;
+        if data[bit / 8] & (1 << (bit % 8)) != 0 {
+            value[i as usize / 8] |= 1 << (i % 8);
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sub_index: u8, bit_offset: u32, bit_size: u32) -> Entry {
+        Entry {
+            sub_index,
+            name: format!("SubIndex {sub_index:03}"),
+            data_type: String::new(),
+            bit_size,
+            bit_offset,
+            access: None,
+            default: None,
+        }
+    }
+
+    #[test]
+    fn split_entries() {
+        let object = Object {
+            index: 0x6000,
+            name: "Inputs".into(),
+            data_type: "DT6000".into(),
+            bit_size: 88,
+            access: None,
+            default: None,
+            entries: vec![
+                // Sub-index 0 takes up 16 bits in a complete access.
+                entry(0, 0, 8),
+                entry(1, 16, 1),
+                entry(3, 24, 16),
+                entry(4, 44, 12),
+                entry(5, 56, 32),
+            ],
+        };
+        let data = [0x05, 0x00, 0xff, 0x34, 0x12, 0xcf, 0xab, 0x99];
+        let values: Vec<_> = split(&object, &data)
+            .into_iter()
+            .map(|(entry, value)| (entry.sub_index, value))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                (0, vec![0x05]),
+                (1, vec![0x01]),
+                (3, vec![0x34, 0x12]),
+                (4, vec![0xbc, 0x0a]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unaligned_bits() {
+        // A BOOL at bit 0 followed by 7 bits of padding.
+        assert_eq!(bits(&[0xff], 0, 1), [0x01]);
+        assert_eq!(bits(&[0xfe], 0, 1), [0x00]);
+        assert_eq!(bits(&[0xff], 1, 7), [0x7f]);
+        // Across a byte boundary.
+        assert_eq!(bits(&[0xf0, 0x0f], 4, 8), [0xff]);
+        assert_eq!(bits(&[0x00, 0x80, 0x01], 15, 2), [0x03]);
+        // Byte aligned, so copied as is.
+        assert_eq!(bits(&[0x11, 0x22, 0x33], 8, 16), [0x22, 0x33]);
+    }
+}
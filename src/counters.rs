@@ -0,0 +1,129 @@
+//! The ESC's error counters, the first thing to look at for flaky cabling.
+
+use ethercrab::SubDeviceRef;
+use serde::Serialize;
+
+use crate::{register, Error};
+
+/// The counters of one port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PortCounters {
+    /// Frames with an invalid length, FCS, or other framing error.
+    pub invalid_frame: u8,
+    /// Physical layer receive errors.
+    pub rx_error: u8,
+    /// Errors already marked by an earlier device and passed on.
+    pub forwarded_rx_error: u8,
+    pub lost_link: u8,
+}
+
+/// The error counters in 0x0300 to 0x0313. They saturate at 255.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ErrorCounters {
+    pub ports: [PortCounters; 4],
+    /// Frames the EtherCAT processing unit rejected.
+    pub processing_unit: u8,
+    pub pdi: u8,
+}
+
+impl ErrorCounters {
+    pub fn parse(registers: [u8; 20]) -> Self {
+        Self {
+            ports: std::array::from_fn(|port| PortCounters {
+                invalid_frame: registers[2 * port],
+                rx_error: registers[2 * port + 1],
+                forwarded_rx_error: registers[8 + port],
+                lost_link: registers[16 + port],
+            }),
+            processing_unit: registers[12],
+            pdi: registers[13],
+        }
+    }
+
+    /// Each counter that isn't zero, as a name like `p1.rx` and its count.
+    pub fn nonzero(&self) -> Vec<(String, u8)> {
+        let mut counts = vec![];
+        for (port, counters) in self.ports.iter().enumerate() {
+            for (name, count) in [
+                ("invalid", counters.invalid_frame),
+                ("rx", counters.rx_error),
+                ("fwd", counters.forwarded_rx_error),
+                ("lost", counters.lost_link),
+            ] {
+                if count != 0 {
+                    counts.push((format!("p{port}.{name}"), count));
+                }
+            }
+        }
+        for (name, count) in [("pu", self.processing_unit), ("pdi", self.pdi)] {
+            if count != 0 {
+                counts.push((name.into(), count));
+            }
+        }
+        counts
+    }
+}
+
+/// Read the error counters of `subdevice`.
+pub async fn read<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<ErrorCounters, Error> {
+    Ok(ErrorCounters::parse(
+        subdevice.register_read(register::ERROR_COUNTERS).await?,
+    ))
+}
+
+/// Reset every error counter of `subdevice` to zero. Writing any one
+/// counter of a group clears the whole group.
+pub async fn clear<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<(), Error> {
+    // RX errors, forwarded RX errors, processing unit, PDI, lost links.
+    for offset in [0, 8, 12, 13, 16] {
+        subdevice
+            .register_write(register::ERROR_COUNTERS + offset, 0u8)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_registers() {
+        // A distinct count in each register, 0x0300 to 0x0313.
+        let counters = ErrorCounters::parse(std::array::from_fn(|i| 100 + i as u8));
+        let expected = |port: u8| PortCounters {
+            invalid_frame: 100 + 2 * port,
+            rx_error: 101 + 2 * port,
+            forwarded_rx_error: 108 + port,
+            lost_link: 116 + port,
+        };
+        assert_eq!(
+            counters,
+            ErrorCounters {
+                ports: [expected(0), expected(1), expected(2), expected(3)],
+                processing_unit: 112,
+                pdi: 113,
+            }
+        );
+    }
+
+    #[test]
+    fn nonzero_counters() {
+        assert!(ErrorCounters::default().nonzero().is_empty());
+
+        let mut registers = [0; 20];
+        registers[3] = 2;
+        registers[9] = 4;
+        registers[13] = 1;
+        registers[19] = 7;
+        assert_eq!(
+            ErrorCounters::parse(registers).nonzero(),
+            [
+                ("p1.rx".to_string(), 2),
+                ("p1.fwd".to_string(), 4),
+                ("p3.lost".to_string(), 7),
+                ("pdi".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Distributed clock (DC) helpers.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethercrab::SubDeviceRef;
+use serde::Serialize;
+
+use crate::{register, Error};
+
+/// Seconds from the Unix epoch to the EtherCAT epoch, 2000-01-01.
+pub const EPOCH_OFFSET_SECS: u64 = 946_684_800;
+
+/// ESC features bit for a distributed clock being available.
+const FEATURE_DC: u16 = 1 << 2;
+/// ESC features bit for the distributed clock being 64 bits wide.
+const FEATURE_DC_64: u16 = 1 << 3;
+/// Sign bit of the system time difference; set if the local copy is behind.
+const DIFFERENCE_NEGATIVE: u32 = 1 << 31;
+
+/// How many bits of system time a device's distributed clock keeps. 32 bit
+/// clocks wrap about every 4.3 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Width {
+    Bits32,
+    Bits64,
+}
+
+impl std::fmt::Display for Width {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Bits32 => write!(f, "32-bit"),
+            Self::Bits64 => write!(f, "64-bit"),
+        }
+    }
+}
+
+/// Whether the ESC features register says the device has a distributed
+/// clock.
+pub fn has_dc(features: u16) -> bool {
+    features & FEATURE_DC != 0
+}
+
+/// The width of the device's distributed clock, if it has one.
+pub fn width(features: u16) -> Option<Width> {
+    if !has_dc(features) {
+        None
+    } else if features & FEATURE_DC_64 != 0 {
+        Some(Width::Bits64)
+    } else {
+        Some(Width::Bits32)
+    }
+}
+
+/// `a - b` in ns for two times from a clock of `width`, allowing for the
+/// clock having wrapped between them.
+pub fn difference(a: u64, b: u64, width: Width) -> i64 {
+    signed(a.wrapping_sub(b), width)
+}
+
+/// A two's complement register from a clock of `width`, sign-extended
+/// from the bits the clock keeps.
+pub fn signed(value: u64, width: Width) -> i64 {
+    match width {
+        Width::Bits32 => i64::from(value as u32 as i32),
+        Width::Bits64 => value as i64,
+    }
+}
+
+/// Nanoseconds since the EtherCAT epoch, as DC system time counts them,
+/// or `None` if `time` is before it, as on a board without an RTC that
+/// booted thinking it's 1970.
+pub fn ethercat_time(time: SystemTime) -> Option<u64> {
+    let since_unix = time.duration_since(UNIX_EPOCH).ok()?;
+    let since_epoch = since_unix
+        .as_nanos()
+        .checked_sub(u128::from(EPOCH_OFFSET_SECS) * 1_000_000_000)?;
+    u64::try_from(since_epoch).ok()
+}
+
+/// How well a device's clock is synchronized.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DcStatus {
+    pub width: Width,
+    /// Added to the local time to get system time, in ns.
+    pub system_time_offset: i64,
+    /// From the reference clock to this device, in ns.
+    pub propagation_delay: u32,
+    /// The local copy of system time minus the last system time received,
+    /// in ns; stays small once the clock is locked.
+    pub difference: i32,
+}
+
+impl std::fmt::Display for DcStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} offset:{}ns delay:{}ns diff:{}ns",
+            self.width, self.system_time_offset, self.propagation_delay, self.difference
+        )
+    }
+}
+
+/// Decode the sign and magnitude system time difference register.
+pub fn decode_difference(raw: u32) -> i32 {
+    let magnitude = (raw & !DIFFERENCE_NEGATIVE) as i32;
+    if raw & DIFFERENCE_NEGATIVE != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Read the DC registers of `subdevice`, or `None` if it has no
+/// distributed clock.
+pub async fn read<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<Option<DcStatus>, Error> {
+    let features: u16 = subdevice.register_read(register::ESC_FEATURES).await?;
+    let Some(width) = width(features) else {
+        return Ok(None);
+    };
+    let system_time_offset: u64 = subdevice
+        .register_read(register::DC_SYSTEM_TIME_OFFSET)
+        .await?;
+    Ok(Some(DcStatus {
+        width,
+        system_time_offset: signed(system_time_offset, width),
+        propagation_delay: subdevice
+            .register_read(register::DC_PROPAGATION_DELAY)
+            .await?,
+        difference: decode_difference(
+            subdevice
+                .register_read(register::DC_SYSTEM_TIME_DIFFERENCE)
+                .await?,
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn difference_32_bit_wraparound() {
+        // a has wrapped past zero, b hasn't yet.
+        assert_eq!(difference(5, 0xffff_fffb, Width::Bits32), 10);
+        assert_eq!(difference(0xffff_fffb, 5, Width::Bits32), -10);
+        // Only the low 32 bits count.
+        assert_eq!(difference(0x1_0000_0005, 0xffff_fffb, Width::Bits32), 10);
+        assert_eq!(difference(0xffff_fffb, 0x2_0000_0005, Width::Bits32), -10);
+        assert_eq!(difference(1000, 400, Width::Bits32), 600);
+        assert_eq!(difference(400, 1000, Width::Bits32), -600);
+    }
+
+    #[test]
+    fn difference_64_bit() {
+        assert_eq!(difference(0x1_0000_0005, 0xffff_fffb, Width::Bits64), 10);
+        assert_eq!(difference(5, 0xffff_fffb, Width::Bits64), -0xffff_fff6);
+        assert_eq!(difference(5, u64::MAX, Width::Bits64), 6);
+    }
+
+    #[test]
+    fn signed_offset() {
+        assert_eq!(signed(0xffff_fff6, Width::Bits32), -10);
+        assert_eq!(signed(0x0000_000a, Width::Bits32), 10);
+        assert_eq!(signed(0xffff_fff6, Width::Bits64), 0xffff_fff6);
+        assert_eq!(signed(u64::MAX - 9, Width::Bits64), -10);
+    }
+
+    #[test]
+    fn ethercat_epoch() {
+        let epoch = UNIX_EPOCH + Duration::from_secs(EPOCH_OFFSET_SECS);
+        assert_eq!(ethercat_time(epoch), Some(0));
+        assert_eq!(ethercat_time(epoch - Duration::from_nanos(1)), None);
+        assert_eq!(ethercat_time(UNIX_EPOCH), None);
+        assert_eq!(ethercat_time(UNIX_EPOCH - Duration::from_secs(1)), None);
+        // 2024-01-01T00:00:00Z
+        let later = UNIX_EPOCH + Duration::new(1_704_067_200, 123);
+        assert_eq!(ethercat_time(later), Some(757_382_400_000_000_123));
+    }
+
+    #[test]
+    fn decode_difference_sign_magnitude() {
+        assert_eq!(decode_difference(10), 10);
+        assert_eq!(decode_difference(DIFFERENCE_NEGATIVE | 10), -10);
+        assert_eq!(decode_difference(0), 0);
+    }
+
+    #[test]
+    fn width_from_features() {
+        assert_eq!(width(0), None);
+        assert_eq!(width(FEATURE_DC), Some(Width::Bits32));
+        assert_eq!(width(FEATURE_DC | FEATURE_DC_64), Some(Width::Bits64));
+    }
+}
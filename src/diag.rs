@@ -0,0 +1,162 @@
+//! Read a device's ETG.1020 diagnosis history, object 0x10F3.
+
+use std::fmt;
+
+use ethercrab::SubDeviceRef;
+
+use crate::Error;
+
+pub const DIAGNOSIS_HISTORY: u16 = 0x10f3;
+
+const MAXIMUM_MESSAGES: u8 = 1;
+const NEWEST_MESSAGE: u8 = 2;
+/// Messages are a ring buffer from this sub-index on.
+const FIRST_MESSAGE: u8 = 6;
+
+/// One entry of the diagnosis history. Any parameters after the fixed
+/// fields are left out.
+#[derive(Debug, Clone, Copy)]
+pub struct Message {
+    pub code: u32,
+    pub flags: u16,
+    /// Looked up in the device's ESI file for the message text.
+    pub text_id: u16,
+    /// DC system time of the message in ns.
+    pub timestamp: u64,
+}
+
+impl Message {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            code: u32::from_le_bytes(data.get(0..4)?.try_into().ok()?),
+            flags: u16::from_le_bytes(data.get(4..6)?.try_into().ok()?),
+            text_id: u16::from_le_bytes(data.get(6..8)?.try_into().ok()?),
+            timestamp: u64::from_le_bytes(data.get(8..16)?.try_into().ok()?),
+        })
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.flags & 0x0f {
+            0 => "info",
+            1 => "warning",
+            2 => "error",
+            _ => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} code:{:#010x} text:{:#06x} at:{}ns",
+            self.kind_name(),
+            self.code,
+            self.text_id,
+            self.timestamp
+        )
+    }
+}
+
+/// Read up to `count` of the newest messages, newest first.
+pub async fn history<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    count: usize,
+) -> Result<Vec<Message>, Error> {
+    let maximum: u8 = subdevice
+        .sdo_read(DIAGNOSIS_HISTORY, MAXIMUM_MESSAGES)
+        .await?;
+    let newest: u8 = subdevice
+        .sdo_read(DIAGNOSIS_HISTORY, NEWEST_MESSAGE)
+        .await?;
+    let mut messages = vec![];
+    for sub_index in ring(newest, maximum, count) {
+        let data: heapless::Vec<u8, 64> = subdevice.sdo_read(DIAGNOSIS_HISTORY, sub_index).await?;
+        // Unused slots of the ring buffer read back empty.
+        let Some(message) = Message::parse(&data) else {
+            break;
+        };
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// The sub-indices of up to `count` messages of a ring buffer of
+/// `maximum` messages, newest first from `newest`, wrapping from the first
+/// slot around to the last. Empty if `newest` isn't in the ring, as when
+/// there haven't been any messages yet.
+fn ring(newest: u8, maximum: u8, count: usize) -> Vec<u8> {
+    let last = (FIRST_MESSAGE - 1).saturating_add(maximum);
+    if !(FIRST_MESSAGE..=last).contains(&newest) {
+        return vec![];
+    }
+    let mut sub_indices = vec![];
+    let mut sub_index = newest;
+    while sub_indices.len() < count.min(usize::from(last - FIRST_MESSAGE + 1)) {
+        sub_indices.push(sub_index);
+        sub_index = if sub_index == FIRST_MESSAGE {
+            last
+        } else {
+            sub_index - 1
+        };
+    }
+    sub_indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_message() {
+        let mut data = vec![];
+        data.extend_from_slice(&0x1234_e001u32.to_le_bytes());
+        data.extend_from_slice(&0x0002u16.to_le_bytes());
+        data.extend_from_slice(&0x8001u16.to_le_bytes());
+        data.extend_from_slice(&1_000_000_007u64.to_le_bytes());
+        // Parameters, which are left out.
+        data.extend_from_slice(&[0xaa, 0xbb]);
+        let message = Message::parse(&data).unwrap();
+        assert_eq!(message.code, 0x1234_e001);
+        assert_eq!(message.text_id, 0x8001);
+        assert_eq!(message.timestamp, 1_000_000_007);
+        assert_eq!(message.kind_name(), "error");
+        assert_eq!(
+            message.to_string(),
+            "error code:0x1234e001 text:0x8001 at:1000000007ns"
+        );
+
+        assert!(Message::parse(&data[..15]).is_none());
+        assert!(Message::parse(&[]).is_none());
+    }
+
+    #[test]
+    fn ring_unwrapped() {
+        // Three messages so far, in 6 to 8; the rest of the slots are
+        // empty, which is where reading stops.
+        assert_eq!(ring(8, 20, 4), [8, 7, 6, 25]);
+        assert_eq!(ring(8, 20, 2), [8, 7]);
+        assert_eq!(ring(6, 20, 1), [6]);
+    }
+
+    #[test]
+    fn ring_wrapped() {
+        // The newest has wrapped around to 7, over the oldest.
+        assert_eq!(ring(7, 5, 10), [7, 6, 10, 9, 8]);
+        assert_eq!(ring(10, 5, 10), [10, 9, 8, 7, 6]);
+        assert_eq!(ring(6, 5, 3), [6, 10, 9]);
+        // A full sub-index range.
+        assert_eq!(ring(6, 250, 2), [6, 255]);
+        assert_eq!(ring(6, 255, 2), [6, 255]);
+    }
+
+    #[test]
+    fn ring_empty() {
+        // No messages yet.
+        assert!(ring(0, 5, 10).is_empty());
+        // Past the end of the ring.
+        assert!(ring(11, 5, 10).is_empty());
+        assert!(ring(6, 0, 10).is_empty());
+        assert!(ring(8, 5, 0).is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+//! Read CANopen Electronic Data Sheet (EDS) files into the same shape as an
+//! ESI file, for CoE devices whose vendors ship one instead.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::esi::{Device, Entry, EsiError, EsiFile, Object};
+
+/// One `[section]` of the file, with its keys lowercased.
+type Section<'a> = HashMap<String, &'a str>;
+
+const OBJECT_TYPE_VAR: u32 = 0x7;
+
+/// Read an EDS file as an ESI file holding the one device it describes.
+pub fn parse(text: &str) -> Result<EsiFile, EsiError> {
+    let sections = sections(text);
+    let info = sections
+        .get("deviceinfo")
+        .ok_or(EsiError::Missing("DeviceInfo"))?;
+    let product_name = info.get("productname").copied().unwrap_or_default();
+
+    let mut objects: BTreeMap<u16, &Section> = BTreeMap::new();
+    let mut sub_objects: BTreeMap<(u16, u8), &Section> = BTreeMap::new();
+    for (name, section) in &sections {
+        match name.split_once("sub") {
+            Some((index, sub_index)) => {
+                let (Ok(index), Ok(sub_index)) = (
+                    u16::from_str_radix(index, 16),
+                    u8::from_str_radix(sub_index, 16),
+                ) else {
+                    continue;
+                };
+                sub_objects.insert((index, sub_index), section);
+            }
+            None if name.len() == 4 => {
+                if let Ok(index) = u16::from_str_radix(name, 16) {
+                    objects.insert(index, section);
+                }
+            }
+            None => {}
+        }
+    }
+
+    let objects = objects
+        .into_iter()
+        .map(|(index, section)| {
+            let entries = sub_objects
+                .range((index, 0)..=(index, u8::MAX))
+                .map(|(&(_, sub_index), section)| (sub_index, *section));
+            parse_object(index, section, entries)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(EsiFile {
+        vendor_id: info.get("vendornumber").map_or(Ok(0), |s| number(s))?,
+        vendor_name: info.get("vendorname").copied().unwrap_or_default().into(),
+        devices: vec![Device {
+            product_code: info.get("productnumber").map_or(Ok(0), |s| number(s))?,
+            revision: info.get("revisionnumber").map_or(Ok(0), |s| number(s))?,
+            type_name: product_name.into(),
+            name: product_name.into(),
+            objects,
+            rx_pdos: vec![],
+            tx_pdos: vec![],
+            sync_managers: vec![],
+            fmmus: vec![],
+            dc_opmodes: vec![],
+            eeprom: None,
+        }],
+    })
+}
+
+fn parse_object<'a>(
+    index: u16,
+    section: &Section,
+    entries: impl Iterator<Item = (u8, &'a Section<'a>)>,
+) -> Result<Object, EsiError> {
+    let name = section.get("parametername").copied().unwrap_or_default();
+    let object_type = section
+        .get("objecttype")
+        .map_or(Ok(OBJECT_TYPE_VAR), |s| number(s))?;
+    if object_type == OBJECT_TYPE_VAR {
+        let (data_type, bit_size) = data_type(section)?;
+        return Ok(Object {
+            index,
+            name: name.into(),
+            data_type,
+            bit_size,
+            access: section.get("accesstype").map(|s| s.to_string()),
+            default: section.get("defaultvalue").map(|s| s.to_string()),
+            entries: vec![],
+        });
+    }
+
+    // Records and arrays are laid out like ESI does it: sub-index 0 takes a
+    // byte, padded to 16 bits, and the rest follow on.
+    let mut bit_offset = 0;
+    let entries = entries
+        .map(|(sub_index, section)| {
+            let (data_type, bit_size) = data_type(section)?;
+            let entry = Entry {
+                sub_index,
+                name: section
+                    .get("parametername")
+                    .copied()
+                    .unwrap_or_default()
+                    .into(),
+                data_type,
+                bit_size,
+                bit_offset,
+                access: section.get("accesstype").map(|s| s.to_string()),
+                default: section.get("defaultvalue").map(|s| s.to_string()),
+            };
+            bit_offset = if sub_index == 0 {
+                16
+            } else {
+                bit_offset + bit_size
+            };
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>, EsiError>>()?;
+    Ok(Object {
+        index,
+        name: name.into(),
+        data_type: format!("DT{index:04X}"),
+        bit_size: bit_offset,
+        access: None,
+        default: None,
+        entries,
+    })
+}
+
+/// The ESI name and bit size of a CANopen data type, per CiA 301. Strings
+/// take their length from the default value. Complex and vendor types are
+/// kept as an opaque `DTxxxx` with no size, like ESI names its own types.
+fn data_type(section: &Section) -> Result<(String, u32), EsiError> {
+    let code = number(
+        section
+            .get("datatype")
+            .ok_or(EsiError::Missing("DataType"))?,
+    )?;
+    let default_len = section.get("defaultvalue").map_or(0, |s| s.len() as u32);
+    let (name, bit_size) = match code {
+        0x01 => ("BOOL", 1),
+        0x02 => ("SINT", 8),
+        0x03 => ("INT", 16),
+        0x04 => ("DINT", 32),
+        0x05 => ("USINT", 8),
+        0x06 => ("UINT", 16),
+        0x07 => ("UDINT", 32),
+        0x08 => ("REAL", 32),
+        0x09 => return Ok((format!("STRING({default_len})"), default_len * 8)),
+        0x0a => ("OCTET_STRING", default_len * 8),
+        0x0b => ("UNICODE_STRING", default_len * 16),
+        0x0c => ("TIME_OF_DAY", 48),
+        0x0d => ("TIME_DIFFERENCE", 48),
+        0x0f => ("DOMAIN", 0),
+        0x10 => ("INT24", 24),
+        0x11 => ("LREAL", 64),
+        0x12 => ("INT40", 40),
+        0x13 => ("INT48", 48),
+        0x14 => ("INT56", 56),
+        0x15 => ("LINT", 64),
+        0x16 => ("UINT24", 24),
+        0x18 => ("UINT40", 40),
+        0x19 => ("UINT48", 48),
+        0x1a => ("UINT56", 56),
+        0x1b => ("ULINT", 64),
+        _ => return Ok((format!("DT{code:04X}"), 0)),
+    };
+    Ok((name.into(), bit_size))
+}
+
+/// Split the file into its `[sections]`, keyed by lowercased name.
+fn sections(text: &str) -> HashMap<String, Section<'_>> {
+    let mut sections: HashMap<String, Section> = HashMap::new();
+    let mut current = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let name = name.trim().to_ascii_lowercase();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if let (Some(current), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .entry(current.clone())
+                .or_default()
+                .insert(key.trim().to_ascii_lowercase(), value.trim());
+        }
+    }
+    sections
+}
+
+/// EDS numbers are decimal or `0x` hex, and may be relative to the node ID,
+/// which doesn't apply to EtherCAT and is taken as 0.
+fn number(s: &str) -> Result<u32, EsiError> {
+    let s = s.trim();
+    let value = s
+        .strip_prefix("$NODEID+")
+        .or_else(|| s.strip_prefix("$NODEID +"))
+        .unwrap_or(s)
+        .trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|_| EsiError::BadNumber(s.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDS: &str = "\
+; A made-up device with a bit of everything.
+[DeviceInfo]
+VendorName=Example
+VendorNumber=0x00000abc
+ProductName=Example Drive
+ProductNumber=42
+RevisionNumber=$NODEID+0x10
+
+[1000]
+ParameterName=Device Type
+ObjectType=0x7
+DataType=0x0007
+AccessType=ro
+DefaultValue=0x00020192
+
+[1008]
+ParameterName=Device Name
+DataType=0x0009
+DefaultValue=Drive
+
+[1018]
+ParameterName=Identity
+ObjectType=0x9
+
+[1018sub0]
+ParameterName=Number of entries
+DataType=0x0005
+DefaultValue=2
+
+[1018sub1]
+ParameterName=Vendor ID
+DataType=0x0007
+
+[1018sub2]
+ParameterName=Position
+DataType=0x0010
+
+[2000]
+ParameterName=Vendor Thing
+DataType=0x0040
+";
+
+    #[test]
+    fn parse_sections() {
+        let sections = sections("; comment\n[One]\nKey = value\n\n[two]\na=b=c\n");
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections["one"]["key"], "value");
+        assert_eq!(sections["two"]["a"], "b=c");
+    }
+
+    #[test]
+    fn parse_number() {
+        assert_eq!(number("0x10").ok(), Some(16));
+        assert_eq!(number("0X10").ok(), Some(16));
+        assert_eq!(number("16").ok(), Some(16));
+        assert_eq!(number("$NODEID+0x10").ok(), Some(16));
+        assert_eq!(number("$NODEID + 16").ok(), Some(16));
+        assert!(number("sixteen").is_err());
+    }
+
+    #[test]
+    fn parse_device() {
+        let file = parse(EDS).unwrap();
+        assert_eq!(file.vendor_id, 0xabc);
+        assert_eq!(file.vendor_name, "Example");
+        let device = &file.devices[0];
+        assert_eq!(device.product_code, 42);
+        assert_eq!(device.revision, 0x10);
+        assert_eq!(device.name, "Example Drive");
+
+        let indices: Vec<_> = device.objects.iter().map(|object| object.index).collect();
+        assert_eq!(indices, [0x1000, 0x1008, 0x1018, 0x2000]);
+
+        let device_type = &device.objects[0];
+        assert_eq!(device_type.data_type, "UDINT");
+        assert_eq!(device_type.bit_size, 32);
+        assert_eq!(device_type.access.as_deref(), Some("ro"));
+        assert_eq!(device_type.default.as_deref(), Some("0x00020192"));
+
+        let name = &device.objects[1];
+        assert_eq!(name.data_type, "STRING(5)");
+        assert_eq!(name.bit_size, 40);
+
+        let identity = &device.objects[2];
+        assert_eq!(identity.data_type, "DT1018");
+        let entries: Vec<_> = identity
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.sub_index,
+                    entry.data_type.as_str(),
+                    entry.bit_size,
+                    entry.bit_offset,
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (0, "USINT", 8, 0),
+                (1, "UDINT", 32, 16),
+                (2, "INT24", 24, 48)
+            ]
+        );
+        assert_eq!(identity.bit_size, 72);
+
+        let vendor = &device.objects[3];
+        assert_eq!(vendor.data_type, "DT0040");
+        assert_eq!(vendor.bit_size, 0);
+    }
+
+    #[test]
+    fn parse_missing_device_info() {
+        assert!(parse("[1000]\nDataType=0x0007\n").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+//! One error type for everything the utilities can fail at, so binaries can
+//! print what went wrong and exit instead of panicking.
+
+use std::{fmt, io};
+
+use ethercrab::error::MailboxError;
+
+use crate::{coe, esi::EsiError, select::SelectError, sii::SiiError};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Talking to the bus failed.
+    Bus(ethercrab::error::Error),
+    /// A SubDevice aborted a CoE SDO transfer.
+    SdoAbort {
+        index: u16,
+        sub_index: u8,
+        code: u32,
+    },
+    Io(io::Error),
+    Esi(EsiError),
+    Sii(SiiError),
+    Select(SelectError),
+    /// An expected network file isn't valid TOML for one.
+    Toml(toml::de::Error),
+    /// Another error, along with what was being done when it happened.
+    Context(String, Box<Error>),
+    /// Anything else, described in full.
+    Other(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bus(err) => write!(f, "{err}"),
+            Self::SdoAbort {
+                index,
+                sub_index,
+                code,
+            } => {
+                write!(
+                    f,
+                    "SDO {index:#06x}:{sub_index:02x} aborted with {code:#010x}"
+                )?;
+                if let Some(description) = coe::abort_description(*code) {
+                    write!(f, " ({description})")?;
+                }
+                Ok(())
+            }
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Esi(err) => write!(f, "{err}"),
+            Self::Sii(err) => write!(f, "{err}"),
+            Self::Select(err) => write!(f, "{err}"),
+            Self::Toml(err) => write!(f, "{err}"),
+            Self::Context(what, err) => write!(f, "{what}: {err}"),
+            Self::Other(what) => write!(f, "{what}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Bus(err) => Some(err),
+            Self::SdoAbort { .. } => None,
+            Self::Io(err) => Some(err),
+            Self::Esi(err) => Some(err),
+            Self::Sii(err) => Some(err),
+            Self::Select(err) => Some(err),
+            Self::Toml(err) => Some(err),
+            Self::Context(_, err) => Some(err.as_ref()),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl From<ethercrab::error::Error> for Error {
+    fn from(err: ethercrab::error::Error) -> Self {
+        match err {
+            ethercrab::error::Error::Mailbox(MailboxError::Aborted {
+                code,
+                address,
+                sub_index,
+            }) => Self::SdoAbort {
+                index: address,
+                sub_index,
+                code: code.into(),
+            },
+            err => Self::Bus(err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<EsiError> for Error {
+    fn from(err: EsiError) -> Self {
+        Self::Esi(err)
+    }
+}
+
+impl From<SiiError> for Error {
+    fn from(err: SiiError) -> Self {
+        Self::Sii(err)
+    }
+}
+
+impl From<SelectError> for Error {
+    fn from(err: SelectError) -> Self {
+        Self::Select(err)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Toml(err)
+    }
+}
+
+/// Say what was being done when an error happened.
+pub trait Context<T> {
+    fn context(self, what: impl Into<String>) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn context(self, what: impl Into<String>) -> Result<T, Error> {
+        self.map_err(|err| Error::Context(what.into(), Box::new(err.into())))
+    }
+}
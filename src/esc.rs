@@ -0,0 +1,168 @@
+//! Identify the EtherCAT SubDevice Controller (ESC) chip in a device and
+//! what it can do.
+
+use std::fmt;
+
+use ethercrab::SubDeviceRef;
+use serde::Serialize;
+
+use crate::{
+    dc::{self, Width},
+    register, Error,
+};
+
+/// ESC features bit for LRW *not* being supported.
+const FEATURE_NO_LRW: u16 = 1 << 9;
+
+/// The ESC information registers, 0x0000 to 0x0009.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EscInfo {
+    pub kind: u8,
+    pub revision: u8,
+    pub build: u16,
+    pub fmmus: u8,
+    pub sync_managers: u8,
+    /// Process data RAM in KiB.
+    pub ram_kib: u8,
+    /// Two bits per port: not implemented, not configured, EBUS, or MII.
+    pub port_descriptor: u8,
+    pub features: u16,
+}
+
+impl EscInfo {
+    pub fn parse(registers: [u8; 10]) -> Self {
+        Self {
+            kind: registers[0],
+            revision: registers[1],
+            build: u16::from_le_bytes([registers[2], registers[3]]),
+            fmmus: registers[4],
+            sync_managers: registers[5],
+            ram_kib: registers[6],
+            port_descriptor: registers[7],
+            features: u16::from_le_bytes([registers[8], registers[9]]),
+        }
+    }
+
+    /// The chip family, for the types Beckhoff and Microchip document.
+    pub fn kind_name(&self) -> Option<&'static str> {
+        Some(match self.kind {
+            0x01 | 0x02 => "ESC10/ESC20",
+            0x03 => "EK1100 FPGA",
+            0x04 => "IP core",
+            0x05 => "internal FPGA",
+            0x11 => "ET1100",
+            0x12 => "ET1200",
+            0xc0 => "LAN9252",
+            _ => return None,
+        })
+    }
+
+    /// The physical layer of each port, or `None` for unused ports.
+    pub fn ports(&self) -> [Option<&'static str>; 4] {
+        std::array::from_fn(|port| match (self.port_descriptor >> (2 * port)) & 0b11 {
+            0b10 => Some("EBUS"),
+            0b11 => Some("MII"),
+            _ => None,
+        })
+    }
+
+    pub fn dc_width(&self) -> Option<Width> {
+        dc::width(self.features)
+    }
+
+    pub fn supports_lrw(&self) -> bool {
+        self.features & FEATURE_NO_LRW == 0
+    }
+}
+
+impl fmt::Display for EscInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind_name() {
+            Some(name) => write!(f, "{name}")?,
+            None => write!(f, "{:#04x}", self.kind)?,
+        }
+        write!(
+            f,
+            " rev:{} build:{:#06x} fmmu:{} sm:{} ram:{}KiB ports:",
+            self.revision, self.build, self.fmmus, self.sync_managers, self.ram_kib
+        )?;
+        let ports: Vec<_> = self
+            .ports()
+            .iter()
+            .map(|port| port.unwrap_or("-"))
+            .collect();
+        write!(f, "{}", ports.join(","))?;
+        match self.dc_width() {
+            Some(width) => write!(f, " dc:{width}")?,
+            None => write!(f, " dc:no")?,
+        }
+        write!(f, " lrw:{}", if self.supports_lrw() { "yes" } else { "no" })
+    }
+}
+
+/// Read the ESC information registers of `subdevice`.
+pub async fn read<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<EscInfo, Error> {
+    Ok(EscInfo::parse(
+        subdevice.register_read(register::ESC_TYPE).await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_registers() {
+        for (registers, ports, dc_width, lrw, display) in [
+            (
+                // ET1100 with a 64-bit clock.
+                [0x11, 0x00, 0x02, 0x00, 0x08, 0x08, 0x08, 0xaf, 0xfc, 0x01],
+                [Some("MII"), Some("MII"), Some("EBUS"), Some("EBUS")],
+                Some(Width::Bits64),
+                true,
+                "ET1100 rev:0 build:0x0002 fmmu:8 sm:8 ram:8KiB ports:MII,MII,EBUS,EBUS dc:64-bit lrw:yes",
+            ),
+            (
+                // ET1200 with a 32-bit clock and no LRW.
+                [0x12, 0x01, 0x03, 0x00, 0x03, 0x04, 0x01, 0x0b, 0x04, 0x02],
+                [Some("MII"), Some("EBUS"), None, None],
+                Some(Width::Bits32),
+                false,
+                "ET1200 rev:1 build:0x0003 fmmu:3 sm:4 ram:1KiB ports:MII,EBUS,-,- dc:32-bit lrw:no",
+            ),
+            (
+                // Unknown ESC, second port not configured, no DC.
+                [0x99, 0x00, 0x00, 0x00, 0x02, 0x02, 0x01, 0x02, 0x00, 0x00],
+                [Some("EBUS"), None, None, None],
+                None,
+                true,
+                "0x99 rev:0 build:0x0000 fmmu:2 sm:2 ram:1KiB ports:EBUS,-,-,- dc:no lrw:yes",
+            ),
+        ] {
+            let info = EscInfo::parse(registers);
+            assert_eq!(info.ports(), ports, "{display}");
+            assert_eq!(info.dc_width(), dc_width, "{display}");
+            assert_eq!(info.supports_lrw(), lrw, "{display}");
+            assert_eq!(info.to_string(), display);
+        }
+    }
+
+    #[test]
+    fn parse_fields() {
+        let info = EscInfo::parse([0x11, 0x02, 0x34, 0x12, 8, 8, 8, 0xaf, 0xfc, 0x01]);
+        assert_eq!(
+            info,
+            EscInfo {
+                kind: 0x11,
+                revision: 2,
+                build: 0x1234,
+                fmmus: 8,
+                sync_managers: 8,
+                ram_kib: 8,
+                port_descriptor: 0xaf,
+                features: 0x01fc,
+            }
+        );
+        assert_eq!(info.kind_name(), Some("ET1100"));
+    }
+}
@@ -0,0 +1,804 @@
+//! Read EtherCAT SubDevice Information (ESI) XML files from device vendors.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use ethercrab::SubDeviceIdentity;
+use roxmltree::{Document, Node};
+
+use crate::eds;
+
+/// Everything in one ESI file.
+#[derive(Debug, Clone)]
+pub struct EsiFile {
+    pub vendor_id: u32,
+    pub vendor_name: String,
+    pub devices: Vec<Device>,
+}
+
+/// One device described by an ESI file.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub product_code: u32,
+    pub revision: u32,
+    /// The short type name, e.g. `EL3002`.
+    pub type_name: String,
+    /// The long, human readable name.
+    pub name: String,
+    pub objects: Vec<Object>,
+    pub rx_pdos: Vec<Pdo>,
+    pub tx_pdos: Vec<Pdo>,
+    pub sync_managers: Vec<SyncManager>,
+    /// What each FMMU is used for, e.g. `Outputs` or `MBoxState`.
+    pub fmmus: Vec<String>,
+    pub dc_opmodes: Vec<DcOpMode>,
+    /// The full EEPROM image, if the file includes one.
+    pub eeprom: Option<Vec<u8>>,
+}
+
+/// A CoE object dictionary entry.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub index: u16,
+    pub name: String,
+    pub data_type: String,
+    pub bit_size: u32,
+    /// e.g. `ro` or `rw`, if the file says.
+    pub access: Option<String>,
+    /// The default value as written in the file, if it gives one.
+    pub default: Option<String>,
+    /// The sub-indices of a record or array; empty for simple objects.
+    pub entries: Vec<Entry>,
+}
+
+/// A sub-index of a record or array object.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub sub_index: u8,
+    pub name: String,
+    pub data_type: String,
+    pub bit_size: u32,
+    pub bit_offset: u32,
+    pub access: Option<String>,
+    pub default: Option<String>,
+}
+
+/// A PDO and its mapping.
+#[derive(Debug, Clone)]
+pub struct Pdo {
+    pub index: u16,
+    pub name: String,
+    /// The sync manager this PDO is assigned to by default.
+    pub sync_manager: Option<u8>,
+    pub fixed: bool,
+    pub mandatory: bool,
+    pub entries: Vec<PdoEntry>,
+}
+
+/// An object mapped into a PDO. Padding has index 0.
+#[derive(Debug, Clone)]
+pub struct PdoEntry {
+    pub index: u16,
+    pub sub_index: u8,
+    pub bit_len: u32,
+    pub name: String,
+    pub data_type: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncManager {
+    /// What this sync manager is used for, e.g. `MBoxOut` or `Inputs`.
+    pub kind: String,
+    pub start_address: u16,
+    pub default_size: Option<u16>,
+    pub control_byte: u8,
+    pub enable: bool,
+}
+
+/// A distributed clocks mode the device supports.
+#[derive(Debug, Clone)]
+pub struct DcOpMode {
+    pub name: String,
+    pub description: String,
+    /// The value written to the DC activation register for this mode.
+    pub assign_activate: u16,
+}
+
+#[derive(Debug)]
+pub enum EsiError {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    /// A required element or attribute wasn't there.
+    Missing(&'static str),
+    /// A number couldn't be parsed.
+    BadNumber(String),
+}
+
+impl fmt::Display for EsiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Xml(err) => write!(f, "invalid XML: {err}"),
+            Self::Missing(what) => write!(f, "missing {what}"),
+            Self::BadNumber(s) => write!(f, "{s:?} isn't a number"),
+        }
+    }
+}
+
+impl std::error::Error for EsiError {}
+
+impl From<io::Error> for EsiError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for EsiError {
+    fn from(err: roxmltree::Error) -> Self {
+        Self::Xml(err)
+    }
+}
+
+impl EsiFile {
+    /// Read an ESI file, or a CANopen EDS file if it ends in `.eds`. Vendors
+    /// often ship these as ISO-8859-1, which is handled as well as UTF-8.
+    pub fn load(path: &Path) -> Result<Self, EsiError> {
+        let bytes = fs::read(path)?;
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(err) => err.into_bytes().into_iter().map(char::from).collect(),
+        };
+        if is_eds(path) {
+            eds::parse(&text)
+        } else {
+            text.parse()
+        }
+    }
+}
+
+fn is_eds(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("eds"))
+}
+
+/// Find the description of the device with `identity`, preferring an exact
+/// revision match and otherwise taking the newest revision.
+pub fn find<'a>(files: &'a [EsiFile], identity: &SubDeviceIdentity) -> Option<&'a Device> {
+    let candidates = files
+        .iter()
+        .filter(|file| file.vendor_id == identity.vendor_id)
+        .flat_map(|file| &file.devices)
+        .filter(|device| device.product_code == identity.product_id);
+    let mut newest: Option<&Device> = None;
+    for device in candidates {
+        if device.revision == identity.revision {
+            return Some(device);
+        }
+        if newest.is_none_or(|newest| device.revision > newest.revision) {
+            newest = Some(device);
+        }
+    }
+    newest
+}
+
+impl std::str::FromStr for EsiFile {
+    type Err = EsiError;
+
+    fn from_str(xml: &str) -> Result<Self, Self::Err> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+        let vendor = child(root, "Vendor").ok_or(EsiError::Missing("Vendor"))?;
+        let devices = child(root, "Descriptions")
+            .and_then(|descriptions| child(descriptions, "Devices"))
+            .ok_or(EsiError::Missing("Devices"))?;
+        Ok(Self {
+            vendor_id: number(text(vendor, "Id").ok_or(EsiError::Missing("Vendor Id"))?)?,
+            vendor_name: text(vendor, "Name").unwrap_or_default().into(),
+            devices: children(devices, "Device")
+                .map(parse_device)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn parse_device(device: Node) -> Result<Device, EsiError> {
+    let device_type = child(device, "Type").ok_or(EsiError::Missing("Device Type"))?;
+    let dictionary = child(device, "Profile").and_then(|profile| child(profile, "Dictionary"));
+    let objects = match dictionary {
+        Some(dictionary) => parse_dictionary(dictionary)?,
+        None => vec![],
+    };
+    Ok(Device {
+        product_code: number(
+            device_type
+                .attribute("ProductCode")
+                .ok_or(EsiError::Missing("ProductCode"))?,
+        )?,
+        revision: device_type
+            .attribute("RevisionNo")
+            .map(number)
+            .transpose()?
+            .unwrap_or(0),
+        type_name: device_type.text().unwrap_or_default().trim().into(),
+        name: text(device, "Name").unwrap_or_default().into(),
+        objects,
+        rx_pdos: children(device, "RxPdo")
+            .map(parse_pdo)
+            .collect::<Result<_, _>>()?,
+        tx_pdos: children(device, "TxPdo")
+            .map(parse_pdo)
+            .collect::<Result<_, _>>()?,
+        sync_managers: children(device, "Sm")
+            .map(parse_sync_manager)
+            .collect::<Result<_, _>>()?,
+        fmmus: children(device, "Fmmu")
+            .map(|fmmu| fmmu.text().unwrap_or_default().trim().into())
+            .collect(),
+        dc_opmodes: child(device, "Dc")
+            .map(|dc| children(dc, "OpMode").map(parse_dc_opmode).collect())
+            .transpose()?
+            .unwrap_or_default(),
+        eeprom: child(device, "Eeprom")
+            .and_then(|eeprom| text(eeprom, "Data"))
+            .map(hex_bytes)
+            .transpose()?,
+    })
+}
+
+/// A data type from the dictionary's `DataTypes` section.
+struct DataType<'a, 'input> {
+    node: Node<'a, 'input>,
+    /// For array types, the first sub-index and the number of elements.
+    array: Option<(u32, u32)>,
+}
+
+fn parse_dictionary(dictionary: Node) -> Result<Vec<Object>, EsiError> {
+    let mut data_types = HashMap::new();
+    if let Some(types) = child(dictionary, "DataTypes") {
+        for data_type in children(types, "DataType") {
+            let array = match child(data_type, "ArrayInfo") {
+                Some(info) => Some((
+                    text(info, "LBound").map(number).transpose()?.unwrap_or(0),
+                    text(info, "Elements").map(number).transpose()?.unwrap_or(0),
+                )),
+                None => None,
+            };
+            if let Some(name) = text(data_type, "Name") {
+                data_types.insert(
+                    name,
+                    DataType {
+                        node: data_type,
+                        array,
+                    },
+                );
+            }
+        }
+    }
+
+    let Some(objects) = child(dictionary, "Objects") else {
+        return Ok(vec![]);
+    };
+    children(objects, "Object")
+        .map(|object| {
+            let data_type = text(object, "Type").unwrap_or_default();
+            let info_names: Vec<&str> = child(object, "Info")
+                .map(|info| {
+                    children(info, "SubItem")
+                        .filter_map(|item| text(item, "Name"))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let entries = match data_types.get(data_type) {
+                Some(definition) => parse_entries(definition, &data_types, &info_names)?,
+                None => vec![],
+            };
+            Ok(Object {
+                index: number(text(object, "Index").ok_or(EsiError::Missing("Object Index"))?)?
+                    as u16,
+                name: text(object, "Name").unwrap_or_default().into(),
+                data_type: data_type.into(),
+                bit_size: text(object, "BitSize")
+                    .map(number)
+                    .transpose()?
+                    .unwrap_or(0),
+                access: access(object),
+                default: child(object, "Info")
+                    .and_then(|info| text(info, "DefaultValue"))
+                    .map(Into::into),
+                entries,
+            })
+        })
+        .collect()
+}
+
+/// The sub-indices of a record or array type, naming array elements after
+/// the object's `Info` entries where there are some.
+fn parse_entries(
+    definition: &DataType,
+    data_types: &HashMap<&str, DataType>,
+    info_names: &[&str],
+) -> Result<Vec<Entry>, EsiError> {
+    let mut entries = vec![];
+    for item in children(definition.node, "SubItem") {
+        let name = text(item, "Name").unwrap_or_default();
+        let data_type = text(item, "Type").unwrap_or_default();
+        let bit_size = text(item, "BitSize").map(number).transpose()?.unwrap_or(0);
+        let bit_offset = text(item, "BitOffs").map(number).transpose()?.unwrap_or(0);
+        let access = access(item);
+
+        if let Some(sub_index) = text(item, "SubIdx") {
+            entries.push(Entry {
+                sub_index: number(sub_index)? as u8,
+                name: name.into(),
+                data_type: data_type.into(),
+                bit_size,
+                bit_offset,
+                access,
+                default: None,
+            });
+            continue;
+        }
+
+        // A SubItem without a sub-index spans the elements of an array type.
+        let Some(DataType {
+            node: array,
+            array: Some((first, elements)),
+        }) = data_types.get(data_type)
+        else {
+            continue;
+        };
+        let base_type = text(*array, "BaseType").unwrap_or_default();
+        let element_size = bit_size.checked_div(*elements).unwrap_or(0);
+        for i in 0..*elements {
+            let sub_index = first + i;
+            entries.push(Entry {
+                sub_index: sub_index as u8,
+                name: info_names
+                    .get(sub_index as usize)
+                    .map_or_else(|| format!("{name} {sub_index}"), |name| name.to_string()),
+                data_type: base_type.into(),
+                bit_size: element_size,
+                bit_offset: bit_offset + i * element_size,
+                access: access.clone(),
+                default: None,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_pdo(pdo: Node) -> Result<Pdo, EsiError> {
+    Ok(Pdo {
+        index: number(text(pdo, "Index").ok_or(EsiError::Missing("Pdo Index"))?)? as u16,
+        name: text(pdo, "Name").unwrap_or_default().into(),
+        sync_manager: pdo
+            .attribute("Sm")
+            .map(number)
+            .transpose()?
+            .map(|sm| sm as u8),
+        fixed: flag(pdo.attribute("Fixed")),
+        mandatory: flag(pdo.attribute("Mandatory")),
+        entries: children(pdo, "Entry")
+            .map(|entry| {
+                Ok(PdoEntry {
+                    index: number(text(entry, "Index").ok_or(EsiError::Missing("Entry Index"))?)?
+                        as u16,
+                    sub_index: text(entry, "SubIndex")
+                        .map(number)
+                        .transpose()?
+                        .unwrap_or(0) as u8,
+                    bit_len: number(
+                        text(entry, "BitLen").ok_or(EsiError::Missing("Entry BitLen"))?,
+                    )?,
+                    name: text(entry, "Name").unwrap_or_default().into(),
+                    data_type: text(entry, "DataType").map(Into::into),
+                })
+            })
+            .collect::<Result<_, EsiError>>()?,
+    })
+}
+
+fn parse_sync_manager(sm: Node) -> Result<SyncManager, EsiError> {
+    Ok(SyncManager {
+        kind: sm.text().unwrap_or_default().trim().into(),
+        start_address: number(
+            sm.attribute("StartAddress")
+                .ok_or(EsiError::Missing("Sm StartAddress"))?,
+        )? as u16,
+        default_size: sm
+            .attribute("DefaultSize")
+            .map(number)
+            .transpose()?
+            .map(|size| size as u16),
+        control_byte: sm
+            .attribute("ControlByte")
+            .map(number)
+            .transpose()?
+            .unwrap_or(0) as u8,
+        enable: flag(sm.attribute("Enable")),
+    })
+}
+
+fn parse_dc_opmode(opmode: Node) -> Result<DcOpMode, EsiError> {
+    Ok(DcOpMode {
+        name: text(opmode, "Name").unwrap_or_default().into(),
+        description: text(opmode, "Desc").unwrap_or_default().into(),
+        assign_activate: text(opmode, "AssignActivate")
+            .map(number)
+            .transpose()?
+            .unwrap_or(0) as u16,
+    })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)?.text().map(str::trim)
+}
+
+fn access(node: Node) -> Option<String> {
+    child(node, "Flags")
+        .and_then(|flags| text(flags, "Access"))
+        .map(Into::into)
+}
+
+/// ESI booleans are `1`/`0` or `true`/`false`.
+fn flag(value: Option<&str>) -> bool {
+    matches!(value, Some("1" | "true"))
+}
+
+/// Binary data is written out as hex, two digits per byte.
+fn hex_bytes(s: &str) -> Result<Vec<u8>, EsiError> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| EsiError::BadNumber(String::from_utf8_lossy(pair).into()))
+        })
+        .collect()
+}
+
+/// ESI numbers are decimal, or hex with a `#x` prefix.
+fn number(s: &str) -> Result<u32, EsiError> {
+    let s = s.trim();
+    match s.strip_prefix("#x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| EsiError::BadNumber(s.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ESI: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<EtherCATInfo>
+  <Vendor>
+    <Id>#x2</Id>
+    <Name>Example</Name>
+  </Vendor>
+  <Descriptions>
+    <Devices>
+      <Device>
+        <Type ProductCode="#x0c1e3052" RevisionNo="#x00140000">EL3102</Type>
+        <Name>EL3102 2Ch. Ana. Input +/-10V, Diff.</Name>
+        <Profile>
+          <Dictionary>
+            <DataTypes>
+              <DataType>
+                <Name>DT1018</Name>
+                <BitSize>144</BitSize>
+                <SubItem>
+                  <SubIdx>0</SubIdx>
+                  <Name>SubIndex 000</Name>
+                  <Type>USINT</Type>
+                  <BitSize>8</BitSize>
+                  <BitOffs>0</BitOffs>
+                  <Flags><Access>ro</Access></Flags>
+                </SubItem>
+                <SubItem>
+                  <SubIdx>1</SubIdx>
+                  <Name>Vendor ID</Name>
+                  <Type>UDINT</Type>
+                  <BitSize>32</BitSize>
+                  <BitOffs>16</BitOffs>
+                </SubItem>
+              </DataType>
+              <DataType>
+                <Name>DT1C12ARR</Name>
+                <BaseType>UINT</BaseType>
+                <BitSize>32</BitSize>
+                <ArrayInfo>
+                  <LBound>1</LBound>
+                  <Elements>2</Elements>
+                </ArrayInfo>
+              </DataType>
+              <DataType>
+                <Name>DT1C12</Name>
+                <BitSize>48</BitSize>
+                <SubItem>
+                  <SubIdx>0</SubIdx>
+                  <Name>SubIndex 000</Name>
+                  <Type>USINT</Type>
+                  <BitSize>8</BitSize>
+                  <BitOffs>0</BitOffs>
+                </SubItem>
+                <SubItem>
+                  <Name>Elements</Name>
+                  <Type>DT1C12ARR</Type>
+                  <BitSize>32</BitSize>
+                  <BitOffs>16</BitOffs>
+                </SubItem>
+              </DataType>
+            </DataTypes>
+            <Objects>
+              <Object>
+                <Index>#x1000</Index>
+                <Name>Device type</Name>
+                <Type>UDINT</Type>
+                <BitSize>32</BitSize>
+                <Info><DefaultValue>#x01551389</DefaultValue></Info>
+                <Flags><Access>ro</Access></Flags>
+              </Object>
+              <Object>
+                <Index>#x1018</Index>
+                <Name>Identity</Name>
+                <Type>DT1018</Type>
+                <BitSize>144</BitSize>
+              </Object>
+              <Object>
+                <Index>#x1c12</Index>
+                <Name>RxPDO assign</Name>
+                <Type>DT1C12</Type>
+                <BitSize>48</BitSize>
+                <Info>
+                  <SubItem><Name>SubIndex 000</Name></SubItem>
+                  <SubItem><Name>First</Name></SubItem>
+                </Info>
+              </Object>
+            </Objects>
+          </Dictionary>
+        </Profile>
+        <Fmmu>Inputs</Fmmu>
+        <Fmmu>MBoxState</Fmmu>
+        <Sm StartAddress="#x1000" DefaultSize="128" ControlByte="#x26" Enable="1">MBoxOut</Sm>
+        <Sm StartAddress="#x1180" ControlByte="#x20" Enable="0">Inputs</Sm>
+        <TxPdo Fixed="1" Mandatory="true" Sm="3">
+          <Index>#x1a00</Index>
+          <Name>AI TxPDO-Map Standard Ch.1</Name>
+          <Entry>
+            <Index>#x6000</Index>
+            <SubIndex>1</SubIndex>
+            <BitLen>1</BitLen>
+            <Name>Underrange</Name>
+            <DataType>BOOL</DataType>
+          </Entry>
+          <Entry>
+            <Index>#x0</Index>
+            <BitLen>15</BitLen>
+          </Entry>
+        </TxPdo>
+        <RxPdo>
+          <Index>#x1600</Index>
+          <Name>Unassigned</Name>
+        </RxPdo>
+        <Dc>
+          <OpMode>
+            <Name>DC</Name>
+            <Desc>DC-Synchron</Desc>
+            <AssignActivate>#x300</AssignActivate>
+          </OpMode>
+        </Dc>
+        <Eeprom>
+          <Data>0A 0b
+            0c0D</Data>
+        </Eeprom>
+      </Device>
+    </Devices>
+  </Descriptions>
+</EtherCATInfo>
+"##;
+
+    fn identity(product_id: u32, revision: u32) -> SubDeviceIdentity {
+        SubDeviceIdentity {
+            vendor_id: 0x2,
+            product_id,
+            revision,
+            serial: 0,
+        }
+    }
+
+    #[test]
+    fn parse_file() {
+        let file: EsiFile = ESI.parse().unwrap();
+        assert_eq!(file.vendor_id, 0x2);
+        assert_eq!(file.vendor_name, "Example");
+        let [device] = file.devices.as_slice() else {
+            panic!("expected one device");
+        };
+        assert_eq!(device.product_code, 0x0c1e3052);
+        assert_eq!(device.revision, 0x0014_0000);
+        assert_eq!(device.type_name, "EL3102");
+        assert_eq!(device.name, "EL3102 2Ch. Ana. Input +/-10V, Diff.");
+        assert_eq!(device.fmmus, ["Inputs", "MBoxState"]);
+        assert_eq!(
+            device.eeprom.as_deref(),
+            Some(&[0x0a, 0x0b, 0x0c, 0x0d][..])
+        );
+
+        let [opmode] = device.dc_opmodes.as_slice() else {
+            panic!("expected one DC mode");
+        };
+        assert_eq!(opmode.name, "DC");
+        assert_eq!(opmode.description, "DC-Synchron");
+        assert_eq!(opmode.assign_activate, 0x300);
+    }
+
+    #[test]
+    fn parse_objects() {
+        let file: EsiFile = ESI.parse().unwrap();
+        let objects = &file.devices[0].objects;
+        let indices: Vec<_> = objects.iter().map(|object| object.index).collect();
+        assert_eq!(indices, [0x1000, 0x1018, 0x1c12]);
+
+        let device_type = &objects[0];
+        assert_eq!(device_type.data_type, "UDINT");
+        assert_eq!(device_type.bit_size, 32);
+        assert_eq!(device_type.access.as_deref(), Some("ro"));
+        assert_eq!(device_type.default.as_deref(), Some("#x01551389"));
+        assert!(device_type.entries.is_empty());
+
+        let entries = |object: &Object| -> Vec<(u8, String, String, u32, u32)> {
+            object
+                .entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.sub_index,
+                        entry.name.clone(),
+                        entry.data_type.clone(),
+                        entry.bit_size,
+                        entry.bit_offset,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            entries(&objects[1]),
+            [
+                (0, "SubIndex 000".into(), "USINT".into(), 8, 0),
+                (1, "Vendor ID".into(), "UDINT".into(), 32, 16),
+            ]
+        );
+        assert_eq!(objects[1].entries[0].access.as_deref(), Some("ro"));
+        // Array elements are named from Info where it has names, and
+        // numbered otherwise.
+        assert_eq!(
+            entries(&objects[2]),
+            [
+                (0, "SubIndex 000".into(), "USINT".into(), 8, 0),
+                (1, "First".into(), "UINT".into(), 16, 16),
+                (2, "Elements 2".into(), "UINT".into(), 16, 32),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_pdos_and_sync_managers() {
+        let file: EsiFile = ESI.parse().unwrap();
+        let device = &file.devices[0];
+
+        let [tx] = device.tx_pdos.as_slice() else {
+            panic!("expected one TxPDO");
+        };
+        assert_eq!(tx.index, 0x1a00);
+        assert_eq!(tx.sync_manager, Some(3));
+        assert!(tx.fixed);
+        assert!(tx.mandatory);
+        let entries: Vec<_> = tx
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.index,
+                    entry.sub_index,
+                    entry.bit_len,
+                    entry.data_type.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [(0x6000, 1, 1, Some("BOOL")), (0x0000, 0, 15, None)]
+        );
+
+        let [rx] = device.rx_pdos.as_slice() else {
+            panic!("expected one RxPDO");
+        };
+        assert_eq!(rx.sync_manager, None);
+        assert!(!rx.fixed);
+        assert!(rx.entries.is_empty());
+
+        let sms: Vec<_> = device
+            .sync_managers
+            .iter()
+            .map(|sm| {
+                (
+                    sm.kind.as_str(),
+                    sm.start_address,
+                    sm.default_size,
+                    sm.control_byte,
+                    sm.enable,
+                )
+            })
+            .collect();
+        assert_eq!(
+            sms,
+            [
+                ("MBoxOut", 0x1000, Some(128), 0x26, true),
+                ("Inputs", 0x1180, None, 0x20, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(matches!("<a".parse::<EsiFile>(), Err(EsiError::Xml(_))));
+        assert!(matches!(
+            "<EtherCATInfo/>".parse::<EsiFile>(),
+            Err(EsiError::Missing("Vendor"))
+        ));
+        let bad_id = ESI.replace("<Id>#x2</Id>", "<Id>two</Id>");
+        assert!(matches!(
+            bad_id.parse::<EsiFile>(),
+            Err(EsiError::BadNumber(_))
+        ));
+    }
+
+    #[test]
+    fn find_revision() {
+        let file: EsiFile = ESI.parse().unwrap();
+        let mut newer = file.devices[0].clone();
+        newer.revision = 0x0015_0000;
+        let files = [EsiFile {
+            devices: vec![file.devices[0].clone(), newer],
+            ..file
+        }];
+
+        let found = |product, revision| find(&files, &identity(product, revision));
+        assert_eq!(
+            found(0x0c1e3052, 0x0014_0000).map(|device| device.revision),
+            Some(0x0014_0000)
+        );
+        // No exact match, so the newest.
+        assert_eq!(
+            found(0x0c1e3052, 0x0010_0000).map(|device| device.revision),
+            Some(0x0015_0000)
+        );
+        assert!(found(0x0c1f3052, 0x0014_0000).is_none());
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number("#x1A").ok(), Some(0x1a));
+        assert_eq!(number(" 26 ").ok(), Some(26));
+        assert!(number("0x1a").is_err());
+        assert_eq!(hex_bytes("00ff 10").ok(), Some(vec![0x00, 0xff, 0x10]));
+        assert!(hex_bytes("0").is_err());
+        assert!(hex_bytes("zz").is_err());
+    }
+}
@@ -0,0 +1,235 @@
+//! Inspect the host and network interface an EtherCAT bus runs on.
+
+use std::{fs, io, path::PathBuf, process::Command};
+
+/// The `CAP_NET_RAW` bit in the Linux capability sets.
+const CAP_NET_RAW: u32 = 13;
+
+/// Offload features that hold back received frames, and their `ethtool -K`
+/// flags.
+pub const LATENCY_OFFLOADS: [(&str, &str); 2] = [
+    ("generic-receive-offload", "gro"),
+    ("large-receive-offload", "lro"),
+];
+
+/// Interrupt coalescing settings that should be 0 for minimum latency.
+pub const LATENCY_COALESCING: [&str; 2] = ["rx-usecs", "tx-usecs"];
+
+/// Whether this process may open the raw sockets EtherCAT needs.
+pub fn has_raw_socket_capability() -> io::Result<bool> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    let effective = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|caps| u64::from_str_radix(caps.trim(), 16).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no CapEff in status"))?;
+    Ok(effective & (1 << CAP_NET_RAW) != 0)
+}
+
+/// Whether the running kernel was built with PREEMPT_RT.
+pub fn is_realtime_kernel() -> bool {
+    if let Ok(realtime) = fs::read_to_string("/sys/kernel/realtime") {
+        return realtime.trim() == "1";
+    }
+    fs::read_to_string("/proc/version")
+        .map(|version| version.contains("PREEMPT_RT"))
+        .unwrap_or(false)
+}
+
+fn sysfs(interface: &str) -> PathBuf {
+    PathBuf::from("/sys/class/net").join(interface)
+}
+
+/// Whether `interface` names a network interface on this host.
+pub fn interface_exists(interface: &str) -> bool {
+    sysfs(interface).exists()
+}
+
+/// The MTU of `interface` in bytes.
+pub fn mtu(interface: &str) -> io::Result<u32> {
+    fs::read_to_string(sysfs(interface).join("mtu"))?
+        .trim()
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// An offload feature as reported by `ethtool -k`.
+pub struct Offload {
+    pub name: String,
+    pub enabled: bool,
+    /// The driver doesn't allow this feature to be changed.
+    pub fixed: bool,
+}
+
+/// The offload features of `interface`.
+pub fn offloads(interface: &str) -> io::Result<Vec<Offload>> {
+    Ok(parse_offloads(&ethtool(&["-k", interface])?))
+}
+
+/// The offload features in the output of `ethtool -k`.
+fn parse_offloads(output: &str) -> Vec<Offload> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(": ")?;
+            let enabled = match value.split_whitespace().next()? {
+                "on" => true,
+                "off" => false,
+                _ => return None,
+            };
+            Some(Offload {
+                name: name.trim().into(),
+                enabled,
+                fixed: value.contains("[fixed]"),
+            })
+        })
+        .collect()
+}
+
+/// The numeric interrupt coalescing settings of `interface`, as reported
+/// by `ethtool -c`.
+pub fn coalescing(interface: &str) -> io::Result<Vec<(String, u32)>> {
+    Ok(parse_coalescing(&ethtool(&["-c", interface])?))
+}
+
+/// The numeric settings in the output of `ethtool -c`.
+fn parse_coalescing(output: &str) -> Vec<(String, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(": ")?;
+            Some((name.trim().into(), value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn ethtool(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("ethtool").args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into())
+}
+
+/// Low-latency NIC settings applied to an interface, which are put back
+/// the way they were when this is dropped.
+pub struct NicTuning {
+    interface: String,
+    /// `ethtool -K` flags that were turned off.
+    offloads: Vec<&'static str>,
+    /// Coalescing settings that were zeroed, with their old values.
+    coalescing: Vec<(String, u32)>,
+}
+
+impl NicTuning {
+    /// Turn off [`LATENCY_OFFLOADS`] and zero [`LATENCY_COALESCING`] on
+    /// `interface`.
+    pub fn apply(interface: &str) -> io::Result<Self> {
+        let mut tuning = Self {
+            interface: interface.into(),
+            offloads: vec![],
+            coalescing: vec![],
+        };
+        for offload in offloads(interface)? {
+            let Some((_, flag)) = LATENCY_OFFLOADS
+                .iter()
+                .find(|(name, _)| *name == offload.name)
+            else {
+                continue;
+            };
+            if offload.enabled && !offload.fixed {
+                ethtool(&["-K", interface, flag, "off"])?;
+                tuning.offloads.push(flag);
+            }
+        }
+        // Plenty of drivers don't support coalescing at all.
+        if let Ok(settings) = coalescing(interface) {
+            for (name, value) in settings {
+                if value != 0 && LATENCY_COALESCING.contains(&name.as_str()) {
+                    ethtool(&["-C", interface, &name, "0"])?;
+                    tuning.coalescing.push((name, value));
+                }
+            }
+        }
+        Ok(tuning)
+    }
+}
+
+impl Drop for NicTuning {
+    fn drop(&mut self) {
+        for flag in &self.offloads {
+            if let Err(err) = ethtool(&["-K", &self.interface, flag, "on"]) {
+                eprintln!("couldn't restore {flag} on {}: {err}", self.interface);
+            }
+        }
+        for (name, value) in &self.coalescing {
+            if let Err(err) = ethtool(&["-C", &self.interface, name, &value.to_string()]) {
+                eprintln!("couldn't restore {name} on {}: {err}", self.interface);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ethtool_offloads() {
+        let output = "Features for enp3s0:
+rx-checksumming: on
+tx-checksumming: on
+\ttx-checksum-ipv4: off [fixed]
+\ttx-checksum-ip-generic: on
+scatter-gather: on
+\ttx-scatter-gather-fraglist: off [fixed]
+tcp-segmentation-offload: on
+generic-receive-offload: on
+large-receive-offload: off [fixed]
+rx-vlan-filter: on [fixed]
+hsr-dup-offload: off [requested on]
+";
+        let offloads: Vec<_> = parse_offloads(output)
+            .into_iter()
+            .map(|offload| (offload.name, offload.enabled, offload.fixed))
+            .collect();
+        let expected = [
+            ("rx-checksumming", true, false),
+            ("tx-checksumming", true, false),
+            ("tx-checksum-ipv4", false, true),
+            ("tx-checksum-ip-generic", true, false),
+            ("scatter-gather", true, false),
+            ("tx-scatter-gather-fraglist", false, true),
+            ("tcp-segmentation-offload", true, false),
+            ("generic-receive-offload", true, false),
+            ("large-receive-offload", false, true),
+            ("rx-vlan-filter", true, true),
+            ("hsr-dup-offload", false, false),
+        ]
+        .map(|(name, enabled, fixed)| (name.to_string(), enabled, fixed));
+        assert_eq!(offloads, expected);
+        assert!(parse_offloads("").is_empty());
+    }
+
+    #[test]
+    fn parse_ethtool_coalescing() {
+        let output = "Coalesce parameters for enp3s0:
+Adaptive RX: off  TX: n/a
+stats-block-usecs: n/a
+sample-interval: n/a
+
+rx-usecs: 3
+rx-frames: n/a
+rx-usecs-irq: n/a
+
+tx-usecs: 0
+tx-frames: 16
+";
+        let expected = [("rx-usecs", 3), ("tx-usecs", 0), ("tx-frames", 16)]
+            .map(|(name, value)| (name.to_string(), value));
+        assert_eq!(parse_coalescing(output), expected);
+        assert!(parse_coalescing("").is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+//! Shared pieces of the EtherCAT utilities.
+
+pub mod al;
+pub mod budget;
+pub mod coe;
+pub mod counters;
+pub mod dc;
+pub mod diag;
+pub mod eds;
+pub mod error;
+pub mod esc;
+pub mod esi;
+pub mod host;
+pub mod mailbox;
+pub mod network;
+pub mod quirk;
+pub mod register;
+pub mod reset;
+pub mod runtime;
+pub mod scaffold;
+pub mod select;
+pub mod shutdown;
+pub mod sii;
+pub mod topology;
+
+pub use error::Error;
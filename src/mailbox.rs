@@ -0,0 +1,173 @@
+//! Check that a device's mailbox sizes agree between its SII, its
+//! SyncManagers, and what it actually sends.
+
+use std::{fmt, time::Duration};
+
+use ethercrab::SubDeviceRef;
+use serde::Serialize;
+
+use crate::{coe, quirk::Quirks, register, sii, Error};
+
+/// The mailbox, CoE, and SDO headers ahead of the data of an SDO response.
+pub const SDO_OVERHEAD: u16 = 16;
+/// How long to wait for a device's PDI to let go of its EEPROM.
+const EEPROM_ACCESS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The sizes of a device's standard mailbox, in bytes.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MailboxSizes {
+    /// MainDevice to SubDevice, as the SII declares it.
+    pub sii_out: u16,
+    /// SubDevice to MainDevice, as the SII declares it.
+    pub sii_in: u16,
+    /// As SyncManager 0 is configured.
+    pub sm_out: u16,
+    /// As SyncManager 1 is configured.
+    pub sm_in: u16,
+    /// Whether a complete access read of 0x1018 came back short; `None`
+    /// if it couldn't be tried.
+    pub truncates: Option<bool>,
+}
+
+impl MailboxSizes {
+    /// What's wrong with the mailbox, with what to do about it.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        for (what, sii, sm) in [
+            ("out", self.sii_out, self.sm_out),
+            ("in", self.sii_in, self.sm_in),
+        ] {
+            if sii != sm {
+                problems.push(format!(
+                    "mailbox {what} is configured for {sm}B but the SII declares {sii}B; the SII header and SyncM category disagree, so fix the EEPROM"
+                ));
+            }
+        }
+        if self.sm_in <= SDO_OVERHEAD {
+            problems.push(format!(
+                "mailbox in is only {}B, too small for any SDO response; fix the EEPROM",
+                self.sm_in
+            ));
+        }
+        if self.truncates == Some(true) {
+            problems.push(
+                "the device cut a complete access response short, so it may truncate other large SDO responses; read objects a sub-index at a time".into(),
+            );
+        }
+        problems
+    }
+}
+
+impl fmt::Display for MailboxSizes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "out:{}B in:{}B", self.sm_out, self.sm_in)?;
+        if (self.sii_out, self.sii_in) != (self.sm_out, self.sm_in) {
+            write!(f, " (SII out:{}B in:{}B)", self.sii_out, self.sii_in)?;
+        }
+        if self.truncates == Some(true) {
+            write!(f, " truncates")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the mailbox sizes of `subdevice`, or `None` if it has no mailbox.
+pub async fn read<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    quirks: &Quirks,
+) -> Result<Option<MailboxSizes>, Error> {
+    let header = sii::with_access(
+        subdevice,
+        EEPROM_ACCESS_TIMEOUT,
+        sii::read_header(subdevice),
+    )
+    .await?;
+    let mailbox = header.standard_mailbox;
+    if mailbox.receive_size == 0 {
+        return Ok(None);
+    }
+    let sm_length = |config: [u8; 8]| u16::from_le_bytes([config[2], config[3]]);
+    let sm_out = sm_length(subdevice.register_read(register::SYNC_MANAGER).await?);
+    let sm_in = sm_length(subdevice.register_read(register::SYNC_MANAGER + 8).await?);
+    let truncates = if header.protocol_names().contains(&"CoE") && !quirks.no_complete_access {
+        truncates(subdevice).await
+    } else {
+        None
+    };
+    Ok(Some(MailboxSizes {
+        sii_out: mailbox.receive_size,
+        sii_in: mailbox.send_size,
+        sm_out,
+        sm_in,
+        truncates,
+    }))
+}
+
+/// Read 0x1018 with complete access and check all the sub-indices it says
+/// it has came back. Plenty of devices don't support complete access, so
+/// an abort means it couldn't be tried.
+async fn truncates<S>(subdevice: &SubDeviceRef<'_, S>) -> Option<bool> {
+    let data = coe::read_complete(subdevice, 0x1018).await.ok()?;
+    is_truncated(&data)
+}
+
+/// Whether a complete access response of 0x1018 is shorter than its
+/// sub-index 0 says, or `None` if it's empty.
+fn is_truncated(data: &[u8]) -> Option<bool> {
+    let entries = usize::from(*data.first()?);
+    // Sub-index 0 is padded to 16 bits, then each entry is 32.
+    Some(data.len() < 2 + 4 * entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(sii: (u16, u16), sm: (u16, u16)) -> MailboxSizes {
+        MailboxSizes {
+            sii_out: sii.0,
+            sii_in: sii.1,
+            sm_out: sm.0,
+            sm_in: sm.1,
+            truncates: Some(false),
+        }
+    }
+
+    #[test]
+    fn problems() {
+        assert!(sizes((128, 128), (128, 128)).problems().is_empty());
+
+        let problems = sizes((128, 256), (128, 128)).problems();
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("mailbox in is configured for 128B but the SII declares 256B")
+        );
+
+        let problems = sizes((64, 16), (32, 16)).problems();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("mailbox out"));
+        assert!(problems[1].starts_with("mailbox in is only 16B"));
+
+        let truncating = MailboxSizes {
+            truncates: Some(true),
+            ..sizes((128, 128), (128, 128))
+        };
+        assert_eq!(truncating.problems().len(), 1);
+        let untried = MailboxSizes {
+            truncates: None,
+            ..sizes((128, 128), (128, 128))
+        };
+        assert!(untried.problems().is_empty());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut identity = vec![4, 0];
+        identity.extend_from_slice(&[0; 16]);
+        assert_eq!(is_truncated(&identity), Some(false));
+        assert_eq!(is_truncated(&identity[..17]), Some(true));
+        assert_eq!(is_truncated(&[4, 0, 1, 2, 3, 4]), Some(true));
+        assert_eq!(is_truncated(&[0, 0]), Some(false));
+        assert_eq!(is_truncated(&[]), None);
+    }
+}
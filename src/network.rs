@@ -0,0 +1,456 @@
+//! Describe the devices a bus is expected to have, and compare a live bus
+//! against that description.
+
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// The devices expected on a bus, in bus order.
+///
+/// As TOML, each device is a `[[device]]` table:
+///
+/// ```toml
+/// [[device]]
+/// name = "EK1100"
+/// vendor = 0x2
+/// product = 0x44c2c52
+/// revision = 0x110000
+/// alias = 0
+/// input_len = 0
+/// output_len = 0
+/// ```
+///
+/// A device's position is its place in the list. `name`, `revision`,
+/// `alias`, and the PDO sizes can be left out to not check them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Network {
+    #[serde(rename = "device", default)]
+    pub devices: Vec<Device>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    /// Only used to label the device in reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub vendor: u32,
+    pub product: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<u16>,
+    /// Input process data size in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_len: Option<usize>,
+    /// Output process data size in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_len: Option<usize>,
+}
+
+impl Device {
+    fn same_kind(&self, other: &Self) -> bool {
+        (self.vendor, self.product) == (other.vendor, other.product)
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{name} ")?;
+        }
+        write!(
+            f,
+            "(vendor:{:#010x} product:{:#010x})",
+            self.vendor, self.product
+        )
+    }
+}
+
+impl Network {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// One way a live bus differs from what's expected. Positions count from 0
+/// in bus order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch {
+    Missing {
+        position: usize,
+        expected: Device,
+    },
+    Unexpected {
+        position: usize,
+        actual: Device,
+    },
+    /// The right device, but somewhere else on the bus.
+    Moved {
+        expected_position: usize,
+        actual_position: usize,
+        device: Device,
+    },
+    Revision {
+        position: usize,
+        expected: u32,
+        actual: Device,
+    },
+    Alias {
+        position: usize,
+        expected: u16,
+        actual: Device,
+    },
+    /// Different PDO sizes, as (input, output) bytes.
+    ProcessData {
+        position: usize,
+        expected: (Option<usize>, Option<usize>),
+        actual: Device,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Missing { position, expected } => write!(f, "#{position} missing {expected}"),
+            Self::Unexpected { position, actual } => write!(f, "#{position} unexpected {actual}"),
+            Self::Moved {
+                expected_position,
+                actual_position,
+                device,
+            } => write!(
+                f,
+                "#{actual_position} {device} should be at #{expected_position}"
+            ),
+            Self::Revision {
+                position,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "#{position} {actual} is revision {:#010x}, expected {expected:#010x}",
+                actual.revision.unwrap_or_default()
+            ),
+            Self::Alias {
+                position,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "#{position} {actual} has alias {:#06x}, expected {expected:#06x}",
+                actual.alias.unwrap_or_default()
+            ),
+            Self::ProcessData {
+                position,
+                expected: (input_len, output_len),
+                actual,
+            } => {
+                let len = |len: Option<usize>| len.map_or("?".into(), |len| format!("{len}B"));
+                write!(
+                    f,
+                    "#{position} {actual} has in:{} out:{}, expected in:{} out:{}",
+                    len(actual.input_len),
+                    len(actual.output_len),
+                    len(*input_len),
+                    len(*output_len)
+                )
+            }
+        }
+    }
+}
+
+/// Find how `actual` differs from `expected`.
+///
+/// Devices are lined up by vendor and product in bus order, so one missing
+/// device doesn't make everything after it look wrong. A device missing in
+/// one place and unexpected in another is reported as moved.
+pub fn compare(expected: &[Device], actual: &[Device]) -> Vec<Mismatch> {
+    // Longest common subsequence of device kinds.
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for (i, e) in expected.iter().enumerate().rev() {
+        for (j, a) in actual.iter().enumerate().rev() {
+            lengths[i][j] = if e.same_kind(a) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut mismatches = vec![];
+    let mut missing = vec![];
+    let mut unexpected = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i].same_kind(&actual[j]) {
+            let (e, a) = (&expected[i], &actual[j]);
+            if let Some(revision) = e.revision.filter(|r| Some(*r) != a.revision) {
+                mismatches.push(Mismatch::Revision {
+                    position: j,
+                    expected: revision,
+                    actual: a.clone(),
+                });
+            }
+            if let Some(alias) = e.alias.filter(|alias| Some(*alias) != a.alias) {
+                mismatches.push(Mismatch::Alias {
+                    position: j,
+                    expected: alias,
+                    actual: a.clone(),
+                });
+            }
+            // Sizes are only known when the bus went to OP.
+            let differs = |expected: Option<usize>, actual: Option<usize>| {
+                expected.is_some() && actual.is_some() && expected != actual
+            };
+            if differs(e.input_len, a.input_len) || differs(e.output_len, a.output_len) {
+                mismatches.push(Mismatch::ProcessData {
+                    position: j,
+                    expected: (e.input_len, e.output_len),
+                    actual: a.clone(),
+                });
+            }
+            i += 1;
+            j += 1;
+        } else if j == actual.len()
+            || (i < expected.len() && lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            missing.push(i);
+            i += 1;
+        } else {
+            unexpected.push(j);
+            j += 1;
+        }
+    }
+
+    for i in missing {
+        let moved_from = unexpected
+            .iter()
+            .position(|&j| expected[i].same_kind(&actual[j]));
+        match moved_from {
+            Some(k) => {
+                let j = unexpected.remove(k);
+                mismatches.push(Mismatch::Moved {
+                    expected_position: i,
+                    actual_position: j,
+                    device: actual[j].clone(),
+                });
+            }
+            None => mismatches.push(Mismatch::Missing {
+                position: i,
+                expected: expected[i].clone(),
+            }),
+        }
+    }
+    for j in unexpected {
+        mismatches.push(Mismatch::Unexpected {
+            position: j,
+            actual: actual[j].clone(),
+        });
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, product: u32) -> Device {
+        Device {
+            name: Some(name.into()),
+            vendor: 0x2,
+            product,
+            revision: None,
+            alias: None,
+            input_len: None,
+            output_len: None,
+        }
+    }
+
+    fn coupler() -> Device {
+        device("EK1100", 0x044c2c52)
+    }
+
+    fn inputs() -> Device {
+        device("EL1008", 0x03f03052)
+    }
+
+    fn outputs() -> Device {
+        device("EL2008", 0x07d83052)
+    }
+
+    fn analog() -> Device {
+        device("EL3102", 0x0c1e3052)
+    }
+
+    #[test]
+    fn same() {
+        let devices = [coupler(), inputs(), outputs()];
+        assert_eq!(compare(&devices, &devices), []);
+        assert_eq!(compare(&[], &[]), []);
+    }
+
+    #[test]
+    fn missing() {
+        let expected = [coupler(), inputs(), outputs(), analog()];
+        let actual = [coupler(), outputs(), analog()];
+        // Only the missing device is reported, not everything after it.
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::Missing {
+                position: 1,
+                expected: inputs(),
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_at_the_end() {
+        let expected = [coupler(), inputs()];
+        let actual = [coupler()];
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::Missing {
+                position: 1,
+                expected: inputs(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unexpected() {
+        let expected = [coupler(), outputs(), analog()];
+        let actual = [coupler(), inputs(), outputs(), analog()];
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::Unexpected {
+                position: 1,
+                actual: inputs(),
+            }]
+        );
+    }
+
+    #[test]
+    fn moved() {
+        let expected = [coupler(), inputs(), outputs(), analog()];
+        let actual = [coupler(), outputs(), analog(), inputs()];
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::Moved {
+                expected_position: 1,
+                actual_position: 3,
+                device: inputs(),
+            }]
+        );
+    }
+
+    #[test]
+    fn swapped_for_another_kind() {
+        let expected = [coupler(), inputs()];
+        let actual = [coupler(), outputs()];
+        assert_eq!(
+            compare(&expected, &actual),
+            [
+                Mismatch::Missing {
+                    position: 1,
+                    expected: inputs(),
+                },
+                Mismatch::Unexpected {
+                    position: 1,
+                    actual: outputs(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn revision() {
+        let expected = [Device {
+            revision: Some(0x0011_0000),
+            ..coupler()
+        }];
+        let actual = [Device {
+            revision: Some(0x0012_0000),
+            ..coupler()
+        }];
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::Revision {
+                position: 0,
+                expected: 0x0011_0000,
+                actual: actual[0].clone(),
+            }]
+        );
+        // Leaving the revision out doesn't check it.
+        assert_eq!(compare(&[coupler()], &actual), []);
+    }
+
+    #[test]
+    fn alias() {
+        let expected = [
+            coupler(),
+            Device {
+                alias: Some(0x10),
+                ..inputs()
+            },
+        ];
+        let actual = [
+            coupler(),
+            Device {
+                alias: Some(0),
+                ..inputs()
+            },
+        ];
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::Alias {
+                position: 1,
+                expected: 0x10,
+                actual: actual[1].clone(),
+            }]
+        );
+    }
+
+    #[test]
+    fn process_data() {
+        let expected = [Device {
+            input_len: Some(4),
+            output_len: Some(0),
+            ..analog()
+        }];
+        let actual = [Device {
+            input_len: Some(8),
+            output_len: Some(0),
+            ..analog()
+        }];
+        assert_eq!(
+            compare(&expected, &actual),
+            [Mismatch::ProcessData {
+                position: 0,
+                expected: (Some(4), Some(0)),
+                actual: actual[0].clone(),
+            }]
+        );
+        // Sizes aren't known unless the bus went to OP.
+        assert_eq!(compare(&expected, &[analog()]), []);
+    }
+
+    #[test]
+    fn load_toml() {
+        let network: Network = toml::from_str(
+            "[[device]]\nname = \"EK1100\"\nvendor = 0x2\nproduct = 0x44c2c52\n\n[[device]]\nvendor = 0x2\nproduct = 0x3f03052\nalias = 16\n",
+        )
+        .unwrap();
+        assert_eq!(
+            network.devices,
+            [
+                coupler(),
+                Device {
+                    name: None,
+                    alias: Some(16),
+                    ..inputs()
+                },
+            ]
+        );
+    }
+}
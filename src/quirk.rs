@@ -0,0 +1,249 @@
+//! A registry of known ways devices deviate from the standard, so the
+//! tools can work around them.
+//!
+//! No quirks ship with the tools; they only come from the user's quirk
+//! file, see [`user_path`].
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ethercrab::SubDeviceIdentity;
+use serde::Deserialize;
+
+use crate::{error::Context, Error};
+
+/// A deviation of a family of devices, and how to work around it.
+///
+/// Quirks are loaded from TOML, each as a `[[quirk]]` table:
+///
+/// ```toml
+/// [[quirk]]
+/// name = "Acme drive is slow to reach SAFE-OP"
+/// vendor = 0x1234
+/// product = 0x10
+/// revision = 0x2
+/// state_timeout_ms = 30000
+/// ```
+///
+/// Leaving out `revision`, or `product` as well, makes the quirk apply more
+/// widely.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Quirk {
+    pub name: String,
+    pub vendor: u32,
+    pub product: Option<u32>,
+    pub revision: Option<u32>,
+    /// How long the device may take to change state, if longer than usual.
+    pub state_timeout_ms: Option<u64>,
+    /// Complete access SDO reads fail or come back wrong.
+    #[serde(default)]
+    pub no_complete_access: bool,
+    /// SDO writes only take effect once the object is read back.
+    #[serde(default)]
+    pub read_after_write: bool,
+}
+
+impl Quirk {
+    pub fn matches(&self, identity: &SubDeviceIdentity) -> bool {
+        self.vendor == identity.vendor_id
+            && self
+                .product
+                .is_none_or(|product| product == identity.product_id)
+            && self
+                .revision
+                .is_none_or(|revision| revision == identity.revision)
+    }
+}
+
+/// Every workaround one device needs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quirks {
+    pub state_timeout: Option<Duration>,
+    pub no_complete_access: bool,
+    pub read_after_write: bool,
+}
+
+impl Quirks {
+    /// How long to wait for the device to change state, given how long
+    /// would do for a well-behaved one.
+    pub fn state_timeout(&self, default: Duration) -> Duration {
+        self.state_timeout
+            .map_or(default, |timeout| timeout.max(default))
+    }
+}
+
+#[derive(Deserialize)]
+struct Registry {
+    #[serde(rename = "quirk", default)]
+    quirks: Vec<Quirk>,
+}
+
+/// Read quirks from the text of a TOML file.
+pub fn parse(text: &str) -> Result<Vec<Quirk>, Error> {
+    let registry: Registry = toml::from_str(text)?;
+    Ok(registry.quirks)
+}
+
+/// Read quirks from a TOML file.
+pub fn load(path: &Path) -> Result<Vec<Quirk>, Error> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// Where the user's own quirks are kept: `$ECAT_QUIRKS`, or else
+/// `~/.config/ecat-utils/quirks.toml`.
+pub fn user_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("ECAT_QUIRKS") {
+        return Some(path.into());
+    }
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".config/ecat-utils/quirks.toml"))
+}
+
+/// The user's quirks, or none if they don't have a quirk file.
+pub fn registry() -> Result<Vec<Quirk>, Error> {
+    match user_path() {
+        Some(path) if path.exists() => {
+            load(&path).context(format!("failed to load {}", path.display()))
+        }
+        _ => Ok(vec![]),
+    }
+}
+
+/// Every workaround `identity` needs, from all the quirks matching it.
+pub fn find(quirks: &[Quirk], identity: &SubDeviceIdentity) -> Quirks {
+    quirks
+        .iter()
+        .filter(|quirk| quirk.matches(identity))
+        .fold(Quirks::default(), |found, quirk| Quirks {
+            state_timeout: found
+                .state_timeout
+                .max(quirk.state_timeout_ms.map(Duration::from_millis)),
+            no_complete_access: found.no_complete_access || quirk.no_complete_access,
+            read_after_write: found.read_after_write || quirk.read_after_write,
+        })
+}
+
+/// The longest state timeout any quirk asks for.
+pub fn longest_state_timeout(quirks: &[Quirk]) -> Option<Duration> {
+    quirks
+        .iter()
+        .filter_map(|quirk| quirk.state_timeout_ms)
+        .max()
+        .map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUIRKS: &str = r#"
+[[quirk]]
+name = "whole vendor"
+vendor = 0x1234
+read_after_write = true
+
+[[quirk]]
+name = "one product"
+vendor = 0x1234
+product = 0x10
+state_timeout_ms = 30000
+
+[[quirk]]
+name = "one revision"
+vendor = 0x1234
+product = 0x10
+revision = 0x2
+no_complete_access = true
+state_timeout_ms = 5000
+"#;
+
+    fn identity(vendor_id: u32, product_id: u32, revision: u32) -> SubDeviceIdentity {
+        SubDeviceIdentity {
+            vendor_id,
+            product_id,
+            revision,
+            serial: 0,
+        }
+    }
+
+    #[test]
+    fn parse_file() {
+        let quirks = parse(QUIRKS).unwrap();
+        assert_eq!(quirks.len(), 3);
+        assert_eq!(quirks[0].name, "whole vendor");
+        assert_eq!(quirks[0].product, None);
+        assert!(quirks[0].read_after_write);
+        assert!(!quirks[0].no_complete_access);
+        assert_eq!(quirks[2].revision, Some(2));
+        assert_eq!(quirks[2].state_timeout_ms, Some(5000));
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse("[[quirk]]\nname = \"no vendor\"\n").is_err());
+        assert!(parse("[[quirk]\n").is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let quirks = parse(QUIRKS).unwrap();
+        let matching = |identity: SubDeviceIdentity| -> Vec<&str> {
+            quirks
+                .iter()
+                .filter(|quirk| quirk.matches(&identity))
+                .map(|quirk| quirk.name.as_str())
+                .collect()
+        };
+        assert_eq!(
+            matching(identity(0x1234, 0x10, 0x2)),
+            ["whole vendor", "one product", "one revision"]
+        );
+        assert_eq!(
+            matching(identity(0x1234, 0x10, 0x3)),
+            ["whole vendor", "one product"]
+        );
+        assert_eq!(matching(identity(0x1234, 0x11, 0x2)), ["whole vendor"]);
+        assert!(matching(identity(0x4321, 0x10, 0x2)).is_empty());
+    }
+
+    #[test]
+    fn find_merges_matches() {
+        let quirks = parse(QUIRKS).unwrap();
+        let found = find(&quirks, &identity(0x1234, 0x10, 0x2));
+        assert_eq!(found.state_timeout, Some(Duration::from_secs(30)));
+        assert!(found.no_complete_access);
+        assert!(found.read_after_write);
+
+        let found = find(&quirks, &identity(0x4321, 0x10, 0x2));
+        assert_eq!(found.state_timeout, None);
+        assert!(!found.no_complete_access);
+        assert!(!found.read_after_write);
+    }
+
+    #[test]
+    fn state_timeout() {
+        let quirks = parse(QUIRKS).unwrap();
+        assert_eq!(
+            longest_state_timeout(&quirks),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(longest_state_timeout(&[]), None);
+
+        let found = find(&quirks, &identity(0x1234, 0x10, 0x2));
+        assert_eq!(
+            found.state_timeout(Duration::from_secs(10)),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            found.state_timeout(Duration::from_secs(60)),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            Quirks::default().state_timeout(Duration::from_secs(10)),
+            Duration::from_secs(10)
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Addresses of ESC registers read directly by the tools.
+
+/// ESC Type, the first of the ESC information registers.
+pub const ESC_TYPE: u16 = 0x0000;
+/// ESC Features Supported, e.g. whether there's a distributed clock.
+pub const ESC_FEATURES: u16 = 0x0008;
+
+/// Configured Station Alias, loaded from the EEPROM at power-up.
+pub const STATION_ALIAS: u16 = 0x0012;
+
+/// DL Status, the link and loop state of each port.
+pub const DL_STATUS: u16 = 0x0110;
+
+/// AL Control, the state the MainDevice last requested.
+pub const AL_CONTROL: u16 = 0x0120;
+/// AL Status, the state the device is in plus the error flag.
+pub const AL_STATUS: u16 = 0x0130;
+/// AL Status Code, why the error flag is set.
+pub const AL_STATUS_CODE: u16 = 0x0134;
+
+/// The first of the error counters, 0x0300 to 0x0313.
+pub const ERROR_COUNTERS: u16 = 0x0300;
+
+/// SII EEPROM configuration: whether the EEPROM is offered to the PDI.
+pub const SII_CONFIG: u16 = 0x0500;
+/// SII EEPROM PDI access state: whether the PDI has taken the EEPROM.
+pub const SII_PDI_ACCESS: u16 = 0x0501;
+/// SII EEPROM control/status, followed by the EEPROM address.
+pub const SII_CONTROL: u16 = 0x0502;
+/// SII EEPROM data.
+pub const SII_DATA: u16 = 0x0508;
+
+/// SyncManager channels, 8 bytes each: start address, length, control,
+/// status, activate, and PDI control.
+pub const SYNC_MANAGER: u16 = 0x0800;
+
+/// DC System Time, the device's copy of the reference clock in ns.
+pub const DC_SYSTEM_TIME: u16 = 0x0910;
+/// DC System Time Offset, from the local time to system time.
+pub const DC_SYSTEM_TIME_OFFSET: u16 = 0x0920;
+/// DC System Time Delay, the propagation delay from the reference clock.
+pub const DC_PROPAGATION_DELAY: u16 = 0x0928;
+/// DC System Time Difference, how far off the local copy of system time is.
+pub const DC_SYSTEM_TIME_DIFFERENCE: u16 = 0x092c;
@@ -0,0 +1,255 @@
+//! A catalog of ways to restore devices to their factory settings.
+
+use std::path::Path;
+
+use ethercrab::{SubDeviceIdentity, SubDeviceRef};
+use serde::Deserialize;
+
+use crate::{quirk::Quirks, Error};
+
+/// The "load" signature that restores defaults when written to 0x1011.
+const LOAD: u32 = u32::from_le_bytes(*b"load");
+
+/// A sequence of SDO writes that resets a family of devices.
+///
+/// Recipes can also be loaded from TOML, each as a `[[recipe]]` table:
+///
+/// ```toml
+/// [[recipe]]
+/// name = "Acme drive reset"
+/// vendor = 0x1234
+/// product = 0x10
+/// note = "power cycle the drive afterwards"
+///
+/// [[recipe.step]]
+/// index = 0x2000
+/// sub_index = 1
+/// value = 1
+/// ```
+///
+/// Leaving out `product`, or `vendor` as well, makes the recipe apply more
+/// widely.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    pub vendor: Option<u32>,
+    pub product: Option<u32>,
+    /// What to do afterwards, e.g. power cycle the device.
+    pub note: Option<String>,
+    #[serde(rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+/// Write `value` to an object as a 32 bit SDO download.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    pub index: u16,
+    pub sub_index: u8,
+    pub value: u32,
+}
+
+#[derive(Deserialize)]
+struct Catalog {
+    #[serde(rename = "recipe", default)]
+    recipes: Vec<Recipe>,
+}
+
+/// The recipes that ship with the tools.
+pub fn builtin() -> Vec<Recipe> {
+    let restore = Step {
+        index: 0x1011,
+        sub_index: 1,
+        value: LOAD,
+    };
+    vec![
+        Recipe {
+            name: "Beckhoff restore default parameters".into(),
+            vendor: Some(0x2),
+            product: None,
+            note: Some("the defaults take effect after the terminal is power cycled".into()),
+            steps: vec![restore.clone()],
+        },
+        Recipe {
+            name: "CiA 301 restore default parameters".into(),
+            vendor: None,
+            product: None,
+            note: Some("most devices need a power cycle before the defaults take effect".into()),
+            steps: vec![restore],
+        },
+    ]
+}
+
+/// Read recipes from the text of a TOML file.
+pub fn parse(text: &str) -> Result<Vec<Recipe>, Error> {
+    let catalog: Catalog = toml::from_str(text)?;
+    // A product ID only means something within its vendor's, so such a
+    // recipe would never match.
+    if let Some(recipe) = catalog
+        .recipes
+        .iter()
+        .find(|recipe| recipe.vendor.is_none() && recipe.product.is_some())
+    {
+        return Err(Error::Other(format!(
+            "recipe {:?} has a product but no vendor",
+            recipe.name
+        )));
+    }
+    Ok(catalog.recipes)
+}
+
+/// Read recipes from a TOML file.
+pub fn load(path: &Path) -> Result<Vec<Recipe>, Error> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// The most specific recipe for `identity`, taking the earliest of equally
+/// specific ones.
+pub fn find<'a>(recipes: &'a [Recipe], identity: &SubDeviceIdentity) -> Option<&'a Recipe> {
+    let specificity = |recipe: &Recipe| match (recipe.vendor, recipe.product) {
+        (Some(vendor), Some(product))
+            if vendor == identity.vendor_id && product == identity.product_id =>
+        {
+            Some(2)
+        }
+        (Some(vendor), None) if vendor == identity.vendor_id => Some(1),
+        (None, None) => Some(0),
+        _ => None,
+    };
+    recipes
+        .iter()
+        .filter_map(|recipe| Some((specificity(recipe)?, recipe)))
+        .rev()
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, recipe)| recipe)
+}
+
+impl Recipe {
+    pub async fn run<S>(
+        &self,
+        subdevice: &SubDeviceRef<'_, S>,
+        quirks: &Quirks,
+    ) -> Result<(), Error> {
+        for step in &self.steps {
+            subdevice
+                .sdo_write(step.index, step.sub_index, step.value)
+                .await?;
+            if quirks.read_after_write {
+                subdevice
+                    .sdo_read::<u32>(step.index, step.sub_index)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES: &str = r#"
+[[recipe]]
+name = "whole vendor"
+vendor = 0x1234
+
+[[recipe.step]]
+index = 0x1011
+sub_index = 1
+value = 0x64616f6c
+
+[[recipe]]
+name = "one product"
+vendor = 0x1234
+product = 0x10
+note = "power cycle it"
+
+[[recipe.step]]
+index = 0x2000
+sub_index = 1
+value = 1
+
+[[recipe.step]]
+index = 0x2000
+sub_index = 2
+value = 0
+
+[[recipe]]
+name = "same product"
+vendor = 0x1234
+product = 0x10
+step = []
+
+[[recipe]]
+name = "everything"
+step = []
+"#;
+
+    fn identity(vendor_id: u32, product_id: u32) -> SubDeviceIdentity {
+        SubDeviceIdentity {
+            vendor_id,
+            product_id,
+            revision: 0,
+            serial: 0,
+        }
+    }
+
+    #[test]
+    fn parse_file() {
+        let recipes = parse(RECIPES).unwrap();
+        assert_eq!(recipes.len(), 4);
+        assert_eq!(recipes[0].steps[0].value, LOAD);
+        assert_eq!(recipes[1].note.as_deref(), Some("power cycle it"));
+        assert_eq!(recipes[1].steps.len(), 2);
+        assert_eq!(recipes[3].vendor, None);
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let no_vendor = "[[recipe]]\nname = \"no vendor\"\nproduct = 0x10\nstep = []\n";
+        assert!(parse(no_vendor).is_err());
+        assert!(parse("[[recipe]]\nname = \"no steps\"\n").is_err());
+        assert!(parse("[[recipe]\n").is_err());
+    }
+
+    #[test]
+    fn find_most_specific() {
+        let recipes = parse(RECIPES).unwrap();
+        let found = |vendor, product| {
+            find(&recipes, &identity(vendor, product)).map(|recipe| recipe.name.as_str())
+        };
+        // The earliest of the two for the product.
+        assert_eq!(found(0x1234, 0x10), Some("one product"));
+        assert_eq!(found(0x1234, 0x11), Some("whole vendor"));
+        assert_eq!(found(0x4321, 0x10), Some("everything"));
+        assert_eq!(
+            find(&recipes[..3], &identity(0x4321, 0x10)).map(|recipe| &recipe.name),
+            None
+        );
+    }
+
+    #[test]
+    fn find_builtin() {
+        let mut recipes = parse(RECIPES).unwrap();
+        recipes.extend(builtin());
+        let found = |vendor, product| {
+            find(&recipes, &identity(vendor, product)).map(|recipe| recipe.name.as_str())
+        };
+        // The user's recipes come first, so win over the builtin ones.
+        assert_eq!(found(0x4321, 0x10), Some("everything"));
+        assert_eq!(
+            found(0x2, 0x07d83052),
+            Some("Beckhoff restore default parameters")
+        );
+
+        let recipes = builtin();
+        assert_eq!(
+            find(&recipes, &identity(0x2, 0x07d83052)).map(|recipe| recipe.name.as_str()),
+            Some("Beckhoff restore default parameters")
+        );
+        assert_eq!(
+            find(&recipes, &identity(0x4321, 0x10)).map(|recipe| recipe.name.as_str()),
+            Some("CiA 301 restore default parameters")
+        );
+    }
+}
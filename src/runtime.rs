@@ -0,0 +1,124 @@
+//! Open the bus and bring its devices up, the setup every tool shares.
+
+use std::{sync::Arc, time::Duration};
+
+use ethercrab::{
+    std::{ethercat_now, tx_rx_task},
+    subdevice_group::PreOp,
+    MainDevice, MainDeviceConfig, PduStorage, SubDeviceGroup, SubDeviceRef, Timeouts,
+};
+
+use crate::{
+    error::Context,
+    quirk::{self, Quirk, Quirks},
+    shutdown::{self, Teardown, TeardownGuard},
+    Error,
+};
+
+/// Maximum data in one PDU.
+pub const MAX_PDU_PAYLOAD: usize = 1100;
+/// Maximum PDU data payload size, including the PDU's own overhead.
+pub const MAX_PDU_DATA: usize = PduStorage::element_size(MAX_PDU_PAYLOAD);
+/// Maximum number of EtherCAT frames that can be in flight at any one time.
+pub const MAX_FRAMES: usize = 16;
+/// The largest PDI the PDU storage can carry. Process data bigger than one
+/// PDU is split across frames, all of which are in flight in one cycle.
+pub const MAX_PDI_LEN: usize = MAX_FRAMES * MAX_PDU_PAYLOAD;
+
+static PDU_STORAGE: PduStorage<MAX_FRAMES, MAX_PDU_DATA> = PduStorage::new();
+
+/// An open EtherCAT bus, with its frames being sent and received in the
+/// background.
+pub struct Bus {
+    maindevice: Arc<MainDevice<'static>>,
+    quirks: Vec<Quirk>,
+}
+
+/// Settings for opening a [`Bus`].
+pub struct Builder {
+    timeouts: Timeouts,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            timeouts: Timeouts {
+                wait_loop_delay: Duration::from_millis(2),
+                mailbox_response: Duration::from_millis(1000),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Builder {
+    /// How long to wait for the devices to change state.
+    pub fn state_transition_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.state_transition = timeout;
+        self
+    }
+
+    /// Open `interface` and start exchanging frames on it. There's only
+    /// one bus per process, and this must be called inside a tokio
+    /// runtime.
+    ///
+    /// The state timeout is stretched to suit the slowest device in the
+    /// quirk registry, since ethercrab applies it to the whole group.
+    pub fn open(mut self, interface: &str) -> Result<Bus, Error> {
+        let quirks = quirk::registry()?;
+        if let Some(timeout) = quirk::longest_state_timeout(&quirks) {
+            self.timeouts.state_transition = self.timeouts.state_transition.max(timeout);
+        }
+        let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+        let maindevice = Arc::new(MainDevice::new(
+            pdu_loop,
+            self.timeouts,
+            MainDeviceConfig::default(),
+        ));
+        let task = tx_rx_task(interface, tx, rx).context(format!("failed to open {interface}"))?;
+        tokio::spawn(task);
+        Ok(Bus { maindevice, quirks })
+    }
+}
+
+impl Bus {
+    /// Open `interface` with the default settings.
+    pub fn open(interface: &str) -> Result<Self, Error> {
+        Builder::default().open(interface)
+    }
+
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    pub fn maindevice(&self) -> &Arc<MainDevice<'static>> {
+        &self.maindevice
+    }
+
+    /// The workarounds `subdevice` needs.
+    pub fn quirks<S>(&self, subdevice: &SubDeviceRef<'_, S>) -> Quirks {
+        quirk::find(&self.quirks, &subdevice.identity())
+    }
+
+    /// Find and configure every device on the bus, bringing them up to
+    /// PRE-OP. They're taken back down to INIT if the group is dropped.
+    pub async fn init<const MAX_SUBDEVICES: usize, const PDI_LEN: usize>(
+        &self,
+    ) -> Result<
+        TeardownGuard<'static, SubDeviceGroup<MAX_SUBDEVICES, PDI_LEN, PreOp>>,
+        ethercrab::error::Error,
+    > {
+        let group = self
+            .maindevice
+            .init_single_group::<MAX_SUBDEVICES, PDI_LEN>(ethercat_now)
+            .await?;
+        Ok(TeardownGuard::new(group, self.maindevice.clone()))
+    }
+
+    /// Take `group` down to INIT, from whatever state it's in.
+    pub async fn close<G: Teardown>(&self, group: TeardownGuard<'_, G>) -> Result<G::Init, Error> {
+        shutdown::into_init(group.into_inner(), &self.maindevice)
+            .await
+            .context("failed to return to INIT")
+    }
+}
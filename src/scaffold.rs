@@ -0,0 +1,665 @@
+//! Generate a starting ethercrab project for a scanned network.
+
+use std::{collections::HashSet, fmt::Write, path::Path};
+
+use ethercrab::SubDeviceIdentity;
+
+/// The rough size of a process data cycle's PDU, which the generated
+/// storage is sized for.
+const PDU_PAYLOAD: usize = 1100;
+/// The smallest PDI the generated project will allow for.
+const MIN_PDI_LEN: usize = 64;
+/// Arrays past this many bytes don't implement `Default`, so are left as
+/// gaps in the generated structs.
+const MAX_ARRAY_LEN: u32 = 32;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// One object in a device's process data. Gaps have index 0.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub index: u16,
+    pub sub_index: u8,
+    pub bits: u32,
+    /// The CANopen data type, or 0 if not known.
+    pub data_type: u8,
+    pub name: Option<String>,
+}
+
+/// A device as it was found on the bus.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub name: String,
+    pub address: u16,
+    pub identity: SubDeviceIdentity,
+    pub inputs: Vec<Entry>,
+    pub outputs: Vec<Entry>,
+}
+
+impl Device {
+    fn pdi_len(&self) -> usize {
+        let bits: u32 = self
+            .inputs
+            .iter()
+            .chain(&self.outputs)
+            .map(|e| e.bits)
+            .sum();
+        bits.div_ceil(8) as usize
+    }
+}
+
+/// A field of a generated process data struct.
+#[derive(PartialEq)]
+struct Field {
+    name: String,
+    ty: String,
+    wire: String,
+}
+
+/// A generated process data struct, before it's given a name.
+#[derive(PartialEq)]
+struct Layout {
+    bytes: u32,
+    fields: Vec<Field>,
+}
+
+/// A crate name for a project in `dir`.
+pub fn package_name(dir: &Path) -> String {
+    let name: String = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    match name.chars().next() {
+        Some('a'..='z') => name,
+        _ => format!("ecat_{name}"),
+    }
+}
+
+pub fn cargo_toml(package: &str) -> String {
+    format!(
+        r#"[package]
+name = "{package}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+ethercrab = "0.5"
+ethercrab-wire = "0.2"
+tokio = {{ version = "1", features = ["macros", "rt-multi-thread", "signal", "time"] }}
+"#
+    )
+}
+
+/// The main.rs of a project that brings `devices` up to OP on
+/// `interface` and runs a cyclic loop over their process data.
+pub fn main_rs(interface: &str, devices: &[Device]) -> String {
+    let pdi_len: usize = devices.iter().map(Device::pdi_len).sum();
+    // Leave room for the network to grow a little before it has to be
+    // regenerated.
+    let max_subdevices = (devices.len() * 2).next_power_of_two().max(2);
+    let max_pdi_len = (pdi_len * 2).next_power_of_two().max(MIN_PDI_LEN);
+
+    // Devices with the same name and mapping share their structs.
+    let mut types: Vec<(String, String, Option<Layout>, Option<Layout>)> = vec![];
+    let mut structs = String::new();
+    let mut blocks = vec![];
+    for (position, device) in devices.iter().enumerate() {
+        let inputs = layout(&device.inputs);
+        let outputs = layout(&device.outputs);
+        let (has_inputs, has_outputs) = (inputs.is_some(), outputs.is_some());
+        if !has_inputs && !has_outputs {
+            continue;
+        }
+        let base = type_name(&device.name);
+        let shared = types
+            .iter()
+            .find(|(other, _, other_inputs, other_outputs)| {
+                *other == base && (other_inputs, other_outputs) == (&inputs, &outputs)
+            });
+        let name = match shared {
+            Some((_, name, ..)) => name.clone(),
+            None => {
+                let name = if types.iter().any(|(other, ..)| *other == base) {
+                    format!("{base}At{position}")
+                } else {
+                    base.clone()
+                };
+                write_structs(&mut structs, &name, &device.name, &inputs, &outputs);
+                types.push((base, name.clone(), inputs, outputs));
+                name
+            }
+        };
+        blocks.push(cycle_block(
+            position,
+            device,
+            &name,
+            has_inputs,
+            has_outputs,
+        ));
+    }
+
+    let traits: Vec<_> = [
+        (
+            types.iter().any(|(_, _, inputs, _)| inputs.is_some()),
+            "EtherCrabWireRead",
+        ),
+        (
+            types.iter().any(|(_, _, _, outputs)| outputs.is_some()),
+            "EtherCrabWireWrite",
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(used, name)| used.then_some(name))
+    .collect();
+    let wire_imports = match traits.as_slice() {
+        [] => String::new(),
+        [name] => format!("use ethercrab_wire::{name};\n"),
+        names => format!("use ethercrab_wire::{{{}}};\n", names.join(", ")),
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"//! Generated by `ecat scaffold` from the network on {interface}.
+//!
+//! The structs follow the PDO mapping the devices had when the network was
+//! scanned; fill in the cyclic loop with what the machine should do.
+
+use std::{{pin::pin, sync::Arc, time::Duration}};
+
+use ethercrab::{{
+    std::{{ethercat_now, tx_rx_task}},
+    MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+}};
+{wire_imports}use tokio::time::MissedTickBehavior;
+
+/// The network interface the EtherCAT bus is connected to.
+const INTERFACE: &str = {interface:?};
+/// Maximum number of SubDevices that can be stored. This must be a power of 2 greater than 1.
+const MAX_SUBDEVICES: usize = {max_subdevices};
+/// Maximum total PDI length. The network had {pdi_len} bytes when scanned.
+const PDI_LEN: usize = {max_pdi_len};
+/// Maximum PDU data payload size.
+const MAX_PDU_DATA: usize = PduStorage::element_size({PDU_PAYLOAD});
+/// Maximum number of EtherCAT frames that can be in flight at any one time.
+const MAX_FRAMES: usize = 16;
+/// How often to exchange process data.
+const CYCLE_TIME: Duration = Duration::from_millis(1);
+
+static PDU_STORAGE: PduStorage<MAX_FRAMES, MAX_PDU_DATA> = PduStorage::new();
+
+/// Who a device on the bus is expected to be.
+struct Identity {{
+    name: &'static str,
+    vendor: u32,
+    product: u32,
+    revision: u32,
+}}
+
+/// The devices on the bus, in order.
+const DEVICES: [Identity; {}] = ["#,
+        devices.len()
+    );
+    for (position, device) in devices.iter().enumerate() {
+        let identity = device.identity;
+        let _ = writeln!(
+            out,
+            "    // #{position} at {:#06x}\n    Identity {{\n        name: {:?},\n        vendor: {:#010x},\n        product: {:#010x},\n        revision: {:#010x},\n    }},",
+            device.address, device.name, identity.vendor_id, identity.product_id, identity.revision
+        );
+    }
+    let _ = write!(out, "];\n{structs}");
+
+    let _ = write!(
+        out,
+        r#"
+#[tokio::main]
+async fn main() -> Result<(), ethercrab::error::Error> {{
+    let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    let maindevice = Arc::new(MainDevice::new(
+        pdu_loop,
+        Timeouts {{
+            wait_loop_delay: Duration::from_millis(2),
+            mailbox_response: Duration::from_millis(1000),
+            ..Default::default()
+        }},
+        MainDeviceConfig::default(),
+    ));
+    tokio::spawn(tx_rx_task(INTERFACE, tx, rx).expect("failed to open the interface"));
+
+    let group = maindevice
+        .init_single_group::<MAX_SUBDEVICES, PDI_LEN>(ethercat_now)
+        .await?;
+    if group.len() != DEVICES.len() {{
+        eprintln!("found {{}} devices, but expected {{}}", group.len(), DEVICES.len());
+        std::process::exit(1);
+    }}
+    for (subdevice, expected) in group.iter(&maindevice).zip(&DEVICES) {{
+        let identity = subdevice.identity();
+        if (identity.vendor_id, identity.product_id) != (expected.vendor, expected.product) {{
+            eprintln!(
+                "{{:#06x}} is {{}}, but expected {{}}",
+                subdevice.configured_address(),
+                subdevice.name(),
+                expected.name
+            );
+            std::process::exit(1);
+        }}
+        if identity.revision != expected.revision {{
+            eprintln!(
+                "{{:#06x}} {{}} is revision {{:#010x}}, not {{:#010x}}",
+                subdevice.configured_address(),
+                subdevice.name(),
+                identity.revision,
+                expected.revision
+            );
+        }}
+    }}
+
+    let group = group.into_op(&maindevice).await?;
+    let mut interval = tokio::time::interval(CYCLE_TIME);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut shutdown = pin!(tokio::signal::ctrl_c());
+    loop {{
+        tokio::select! {{
+            _ = &mut shutdown => break,
+            _ = interval.tick() => {{}}
+        }}
+        group.tx_rx(&maindevice).await?;
+{}    }}
+
+    let group = group.into_safe_op(&maindevice).await?;
+    let group = group.into_pre_op(&maindevice).await?;
+    group.into_init(&maindevice).await?;
+    Ok(())
+}}
+"#,
+        blocks.concat()
+    );
+    out
+}
+
+fn write_structs(
+    out: &mut String,
+    name: &str,
+    device: &str,
+    inputs: &Option<Layout>,
+    outputs: &Option<Layout>,
+) {
+    for (layout, direction, derive) in [
+        (inputs, "Inputs", "EtherCrabWireRead"),
+        (outputs, "Outputs", "EtherCrabWireWrite"),
+    ] {
+        let Some(layout) = layout else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "\n/// {direction} of {device}.\n#[derive(Debug, Default, {derive})]\n#[wire(bytes = {})]\nstruct {name}{direction} {{",
+            layout.bytes
+        );
+        for field in &layout.fields {
+            let _ = writeln!(
+                out,
+                "    #[wire({})]\n    {}: {},",
+                field.wire, field.name, field.ty
+            );
+        }
+        let _ = writeln!(out, "}}");
+    }
+}
+
+/// The part of the cyclic loop that handles one device.
+fn cycle_block(
+    position: usize,
+    device: &Device,
+    name: &str,
+    inputs: bool,
+    outputs: bool,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "\n        // #{position} {} at {:#06x}\n        {{\n            let subdevice = group.subdevice(&maindevice, {position})?;\n            let io = subdevice.io_raw();",
+        device.name, device.address
+    );
+    if inputs {
+        let _ = writeln!(
+            out,
+            "            let _inputs = {name}Inputs::unpack_from_slice(&io.inputs())\n                .expect(\"inputs fit the PDI\");"
+        );
+    }
+    if outputs {
+        let _ = writeln!(
+            out,
+            "            let outputs = {name}Outputs::default();\n            outputs\n                .pack_to_slice(&mut io.outputs())\n                .expect(\"outputs fit the PDI\");"
+        );
+    }
+    let _ = writeln!(out, "        }}");
+    out
+}
+
+/// Lay out `entries` as struct fields, or `None` if there's nothing in
+/// them to read or write.
+fn layout(entries: &[Entry]) -> Option<Layout> {
+    let mut fields: Vec<Field> = vec![];
+    let mut named = vec![];
+    let mut offset = 0;
+    let mut skip = 0;
+    for entry in entries {
+        let aligned = offset % 8 == 0;
+        offset += entry.bits;
+        let kind = match field_type(entry.bits, entry.data_type) {
+            _ if entry.index == 0 => None,
+            Some(ty) if entry.bits <= 8 || aligned => {
+                Some((ty.to_string(), format!("bits = {}", entry.bits)))
+            }
+            None if aligned && entry.bits % 8 == 0 && entry.bits / 8 <= MAX_ARRAY_LEN => Some((
+                format!("[u8; {}]", entry.bits / 8),
+                format!("bytes = {}", entry.bits / 8),
+            )),
+            _ => None,
+        };
+        let Some((ty, mut wire)) = kind else {
+            skip += entry.bits;
+            continue;
+        };
+        if skip > 0 {
+            wire = format!("pre_skip = {skip}, {wire}");
+            skip = 0;
+        }
+        let name = entry.name.as_deref().and_then(field_name);
+        fields.push(Field {
+            name: name.unwrap_or_else(|| index_name(entry)),
+            ty,
+            wire,
+        });
+        named.push(entry);
+    }
+
+    // Entries often share a name across channels, so tell those apart by
+    // their index.
+    let mut names = HashSet::new();
+    let duplicates: HashSet<String> = fields
+        .iter()
+        .filter(|field| !names.insert(field.name.clone()))
+        .map(|field| field.name.clone())
+        .collect();
+    for (field, entry) in fields.iter_mut().zip(named) {
+        if duplicates.contains(&field.name) {
+            field.name = format!("{}_{}", field.name, index_name(entry));
+        }
+    }
+
+    let bytes = offset.div_ceil(8);
+    let last = fields.last_mut()?;
+    let trailing = bytes * 8 - offset + skip;
+    if trailing > 0 {
+        last.wire = format!("{}, post_skip = {trailing}", last.wire);
+    }
+    Some(Layout { bytes, fields })
+}
+
+/// A field name for a PDO entry from its index and sub-index.
+fn index_name(entry: &Entry) -> String {
+    format!("x{:04x}_{:02x}", entry.index, entry.sub_index)
+}
+
+/// The Rust type for a PDO entry, going by its CANopen data type where
+/// that's known.
+fn field_type(bits: u32, data_type: u8) -> Option<&'static str> {
+    Some(match (bits, data_type) {
+        (1, _) => "bool",
+        (8, 0x02) => "i8",
+        (16, 0x03) => "i16",
+        (32, 0x04) => "i32",
+        (64, 0x15) => "i64",
+        (32, 0x08) => "f32",
+        (64, 0x11) => "f64",
+        (2..=8, _) => "u8",
+        (9..=16, _) => "u16",
+        (17..=32, _) => "u32",
+        (33..=64, _) => "u64",
+        _ => return None,
+    })
+}
+
+/// A snake_case field name from a PDO entry name.
+fn field_name(name: &str) -> Option<String> {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    let out = out.trim_end_matches('_');
+    if out.is_empty() {
+        return None;
+    }
+    Some(match out.chars().next() {
+        Some('0'..='9') => format!("x{out}"),
+        _ if KEYWORDS.contains(&out) => format!("{out}_"),
+        _ => out.to_string(),
+    })
+}
+
+/// A CamelCase type name from a device name.
+fn type_name(name: &str) -> String {
+    let name: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+        })
+        .collect();
+    match name.chars().next() {
+        Some('A'..='Z') => name,
+        _ => format!("Device{name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(index: u16, bits: u32, data_type: u8, name: &str) -> Entry {
+        Entry {
+            index,
+            sub_index: 1,
+            bits,
+            data_type,
+            name: Some(name.to_string()),
+        }
+    }
+
+    fn gap(bits: u32) -> Entry {
+        Entry {
+            index: 0,
+            sub_index: 0,
+            bits,
+            data_type: 0,
+            name: None,
+        }
+    }
+
+    fn fields(layout: &Layout) -> Vec<(&str, &str, &str)> {
+        layout
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.ty.as_str(), field.wire.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn layout_skips() {
+        let layout = layout(&[
+            entry(0x6000, 1, 0x01, "Input 1"),
+            gap(7),
+            entry(0x6010, 16, 0x03, "Value"),
+            entry(0x6020, 4, 0, "Status"),
+            // Unaligned and wider than a byte, so it can't be a field.
+            entry(0x6030, 12, 0, "Counter"),
+            entry(0x6040, 96, 0, "Raw"),
+            entry(0x6050, 4, 0, "Status"),
+        ])
+        .unwrap();
+        assert_eq!(layout.bytes, 18);
+        assert_eq!(
+            fields(&layout),
+            [
+                ("input_1", "bool", "bits = 1"),
+                ("value", "i16", "pre_skip = 7, bits = 16"),
+                ("status_x6020_01", "u8", "bits = 4"),
+                ("raw", "[u8; 12]", "pre_skip = 12, bytes = 12"),
+                ("status_x6050_01", "u8", "bits = 4, post_skip = 4"),
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_trailing_gap() {
+        let layout = layout(&[entry(0x7000, 1, 0x01, "Output"), gap(15)]).unwrap();
+        assert_eq!(layout.bytes, 2);
+        assert_eq!(
+            fields(&layout),
+            [("output", "bool", "bits = 1, post_skip = 15")]
+        );
+    }
+
+    #[test]
+    fn layout_nothing() {
+        assert!(layout(&[]).is_none());
+        assert!(layout(&[gap(16)]).is_none());
+        // Too big to derive Default for.
+        assert!(layout(&[entry(0x6000, 8 * (MAX_ARRAY_LEN + 1), 0, "Blob")]).is_none());
+    }
+
+    #[test]
+    fn layout_unnamed() {
+        let unnamed = Entry {
+            name: None,
+            ..entry(0x6000, 8, 0, "")
+        };
+        let punctuation = entry(0x6000, 8, 0, "--");
+        for entry in [unnamed, punctuation] {
+            let layout = layout(&[entry]).unwrap();
+            assert_eq!(fields(&layout), [("x6000_01", "u8", "bits = 8")]);
+        }
+    }
+
+    #[test]
+    fn field_names() {
+        for (name, expected) in [
+            ("Status Word", Some("status_word")),
+            ("Input 1", Some("input_1")),
+            ("  Actual-Position (raw) ", Some("actual_position_raw")),
+            ("Type", Some("type_")),
+            ("1st channel", Some("x1st_channel")),
+            ("---", None),
+            ("", None),
+        ] {
+            assert_eq!(field_name(name).as_deref(), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn type_names() {
+        for (name, expected) in [
+            ("EL1008", "El1008"),
+            ("EK1100 coupler", "Ek1100Coupler"),
+            ("3-axis drive", "Device3AxisDrive"),
+            ("", "Device"),
+        ] {
+            assert_eq!(type_name(name), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn package_names() {
+        for (dir, expected) in [
+            ("/tmp/My Project", "my_project"),
+            ("machine-io", "machine-io"),
+            ("/tmp/2-robot", "ecat_2-robot"),
+            ("/", "ecat_"),
+        ] {
+            assert_eq!(package_name(Path::new(dir)), expected, "{dir:?}");
+        }
+    }
+
+    #[test]
+    fn field_types() {
+        for (bits, data_type, expected) in [
+            (1, 0x01, Some("bool")),
+            (1, 0, Some("bool")),
+            (8, 0x02, Some("i8")),
+            (8, 0x05, Some("u8")),
+            (4, 0, Some("u8")),
+            (16, 0x03, Some("i16")),
+            (12, 0, Some("u16")),
+            (32, 0x04, Some("i32")),
+            (32, 0x08, Some("f32")),
+            (24, 0, Some("u32")),
+            (64, 0x15, Some("i64")),
+            (64, 0x11, Some("f64")),
+            (48, 0, Some("u64")),
+            (0, 0, None),
+            (65, 0, None),
+        ] {
+            assert_eq!(field_type(bits, data_type), expected, "{bits} {data_type}");
+        }
+    }
+
+    #[test]
+    fn shared_structs() {
+        let device = |address, inputs| Device {
+            name: "EL1008".into(),
+            address,
+            identity: SubDeviceIdentity {
+                vendor_id: 2,
+                product_id: 0x03f03052,
+                revision: 0x00110000,
+                serial: 0,
+            },
+            inputs,
+            outputs: vec![],
+        };
+        let eight = || vec![entry(0x6000, 8, 0, "Inputs")];
+        let sixteen = || vec![entry(0x6000, 16, 0, "Inputs")];
+        let main = main_rs(
+            "eth0",
+            &[
+                device(0x1000, eight()),
+                device(0x1001, sixteen()),
+                device(0x1002, sixteen()),
+                device(0x1003, eight()),
+            ],
+        );
+        assert_eq!(main.matches("struct El1008Inputs {").count(), 1);
+        assert_eq!(main.matches("struct El1008At1Inputs {").count(), 1);
+        assert!(!main.contains("El1008At2"));
+        assert!(!main.contains("El1008At3"));
+        assert_eq!(main.matches("El1008Inputs::unpack_from_slice").count(), 2);
+        assert_eq!(
+            main.matches("El1008At1Inputs::unpack_from_slice").count(),
+            2
+        );
+        assert!(main.contains("use ethercrab_wire::EtherCrabWireRead;\n"));
+    }
+}
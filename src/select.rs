@@ -0,0 +1,238 @@
+//! Pick out a SubDevice by name, EtherCAT address, alias, or position on the
+//! bus.
+
+use std::{fmt, str::FromStr};
+
+use ethercrab::SubDeviceRef;
+
+/// One way of naming a SubDevice on the command line.
+///
+/// - `@0x1001` or `@4097` is a configured station address
+/// - `#3` is a position on the bus, counting from 0
+/// - `alias:0x10` or `alias:16` is a station alias
+/// - `EL3*` or `EL300?` is a glob over device names
+/// - `"EL3002"` is a device name taken as-is, e.g. for names starting with
+///   `@` or `#`, or containing `*`
+/// - anything else is a device name
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Name(String),
+    Glob(String),
+    Address(u16),
+    Alias(u16),
+    Position(usize),
+}
+
+fn number(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Match `name` against a pattern where `*` is any run of characters and
+/// `?` is any one character.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // The last `*` seen, and where in the name it started matching.
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` swallow one more character and try again.
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl FromStr for Selector {
+    type Err = SelectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SelectError::Invalid(s.into());
+        if let Some(address) = s.strip_prefix('@') {
+            number(address).map(Self::Address).ok_or_else(invalid)
+        } else if let Some(position) = s.strip_prefix('#') {
+            position.parse().map(Self::Position).map_err(|_| invalid())
+        } else if let Some(alias) = s.strip_prefix("alias:") {
+            number(alias).map(Self::Alias).ok_or_else(invalid)
+        } else if let Some(name) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            Ok(Self::Name(name.into()))
+        } else if s.is_empty() {
+            Err(invalid())
+        } else if s.contains(['*', '?']) {
+            Ok(Self::Glob(s.into()))
+        } else {
+            Ok(Self::Name(s.into()))
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // Quote names that would otherwise read back as something else.
+            Self::Name(name) if name.parse() != Ok(self.clone()) => write!(f, "\"{name}\""),
+            Self::Name(name) | Self::Glob(name) => write!(f, "{name}"),
+            Self::Address(address) => write!(f, "@{address:#06x}"),
+            Self::Alias(alias) => write!(f, "alias:{alias:#06x}"),
+            Self::Position(position) => write!(f, "#{position}"),
+        }
+    }
+}
+
+impl Selector {
+    /// Whether the SubDevice at `position` matches.
+    pub fn matches<S>(&self, position: usize, subdevice: &SubDeviceRef<'_, S>) -> bool {
+        match self {
+            Self::Name(name) => subdevice.name() == name,
+            Self::Glob(pattern) => glob_matches(pattern, subdevice.name()),
+            Self::Address(address) => subdevice.configured_address() == *address,
+            Self::Alias(alias) => subdevice.alias_address() == *alias,
+            Self::Position(p) => position == *p,
+        }
+    }
+
+    /// Find the one SubDevice this selects, in bus order.
+    pub fn resolve<'a, S>(
+        &self,
+        subdevices: impl IntoIterator<Item = SubDeviceRef<'a, S>>,
+    ) -> Result<SubDeviceRef<'a, S>, SelectError> {
+        let mut matches: Vec<_> = subdevices
+            .into_iter()
+            .enumerate()
+            .filter(|(position, subdevice)| self.matches(*position, subdevice))
+            .collect();
+        match matches.len() {
+            0 => Err(SelectError::NoMatch(self.clone())),
+            1 => Ok(matches.remove(0).1),
+            _ => Err(SelectError::Ambiguous(
+                self.clone(),
+                matches
+                    .iter()
+                    .map(|(position, subdevice)| Candidate {
+                        position: *position,
+                        address: subdevice.configured_address(),
+                        name: subdevice.name().into(),
+                    })
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// A SubDevice that matched an ambiguous selector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub position: usize,
+    pub address: u16,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectError {
+    /// The selector couldn't be parsed.
+    Invalid(String),
+    NoMatch(Selector),
+    /// More than one SubDevice matched.
+    Ambiguous(Selector, Vec<Candidate>),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid(s) => write!(
+                f,
+                "{s:?} isn't a device name, @address, #position, or alias:n"
+            ),
+            Self::NoMatch(selector) => write!(f, "no device matches {selector}"),
+            Self::Ambiguous(selector, candidates) => {
+                write!(f, "{selector} matches more than one device; pick one of")?;
+                for candidate in candidates {
+                    write!(f, " #{} @{:#06x}", candidate.position, candidate.address)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelectError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let parse = |s: &str| s.parse::<Selector>();
+        assert_eq!(parse("EL3002"), Ok(Selector::Name("EL3002".into())));
+        assert_eq!(parse("@0x1001"), Ok(Selector::Address(0x1001)));
+        assert_eq!(parse("@4097"), Ok(Selector::Address(0x1001)));
+        assert_eq!(parse("#3"), Ok(Selector::Position(3)));
+        assert_eq!(parse("alias:0x10"), Ok(Selector::Alias(16)));
+        assert_eq!(parse("alias:16"), Ok(Selector::Alias(16)));
+        assert_eq!(parse("EL3*"), Ok(Selector::Glob("EL3*".into())));
+        assert_eq!(parse("EL300?"), Ok(Selector::Glob("EL300?".into())));
+        assert_eq!(parse("\"#1 axis\""), Ok(Selector::Name("#1 axis".into())));
+        assert_eq!(parse("\"EL3*\""), Ok(Selector::Name("EL3*".into())));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for s in ["", "@", "@0xfffff", "@bus", "#", "#-1", "alias:", "alias:x"] {
+            assert_eq!(
+                s.parse::<Selector>(),
+                Err(SelectError::Invalid(s.into())),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "EL3002",
+            "@0x1001",
+            "#3",
+            "alias:0x0010",
+            "EL3*",
+            "\"#1 axis\"",
+            "\"EL3*\"",
+        ] {
+            let selector: Selector = s.parse().unwrap();
+            assert_eq!(selector.to_string(), s);
+            assert_eq!(selector.to_string().parse(), Ok(selector));
+        }
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_matches("EL3*", "EL3002"));
+        assert!(glob_matches("EL3*", "EL3"));
+        assert!(glob_matches("*02", "EL3002"));
+        assert!(glob_matches("EL*0*2", "EL3002"));
+        assert!(glob_matches("EL300?", "EL3002"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("EL3*", "EK1100"));
+        assert!(!glob_matches("EL300?", "EL30021"));
+        assert!(!glob_matches("EL?", "EL"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Bring a SubDevice group back down to INIT when a tool is done with the
+//! bus, or is interrupted.
+
+use std::{future::Future, io::Write, ops::Deref, sync::Arc};
+
+use ethercrab::{
+    error::Error,
+    subdevice_group::{Init, Op, PreOp, SafeOp},
+    Command, MainDevice, SubDeviceGroup,
+};
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{al, register};
+
+/// Run `work` until it's done or the process gets SIGINT or SIGTERM,
+/// returning `None` if it was interrupted.
+///
+/// Interrupting drops `work`, and so any [`TeardownGuard`] it holds, which
+/// walks its group back to INIT before this returns. Output is flushed
+/// either way.
+pub async fn run_until_shutdown<T>(work: impl Future<Output = T>) -> Option<T> {
+    let mut terminate = signal(SignalKind::terminate()).expect("can listen for SIGTERM");
+    let output = tokio::select! {
+        output = work => Some(output),
+        _ = tokio::signal::ctrl_c() => None,
+        _ = terminate.recv() => None,
+    };
+    if output.is_none() {
+        eprintln!("interrupted");
+    }
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    output
+}
+
+/// A group that can be stepped down to INIT one state at a time.
+pub trait Teardown: Sized {
+    /// The same group in INIT.
+    type Init;
+
+    /// Walk the group down to INIT. If a transition fails, this prints
+    /// which, asks every device to go straight to INIT, and returns that
+    /// first error.
+    fn teardown(
+        self,
+        maindevice: &MainDevice<'_>,
+    ) -> impl Future<Output = Result<Self::Init, Error>>;
+}
+
+/// Step `group` down from whatever state it's in to INIT.
+pub async fn into_init<G: Teardown>(
+    group: G,
+    maindevice: &MainDevice<'_>,
+) -> Result<G::Init, Error> {
+    group.teardown(maindevice).await
+}
+
+/// Pass on the result of one step down, first reporting it if it failed
+/// and asking every device to go to INIT. A failed transition takes the
+/// group with it, so this is the only way left to reach the later states.
+async fn step<T>(
+    result: Result<T, Error>,
+    from: &'static str,
+    to: &'static str,
+    maindevice: &MainDevice<'_>,
+) -> Result<T, Error> {
+    let Err(err) = result else {
+        return result;
+    };
+    eprintln!("failed to go from {from} to {to}: {err}");
+    if let Err(err) = request_init(maindevice).await {
+        eprintln!("failed to ask every device to go to INIT: {err}");
+    }
+    Err(err)
+}
+
+/// Ask every device on the bus to go to INIT, without needing the group
+/// they're in.
+pub async fn request_init(maindevice: &MainDevice<'_>) -> Result<(), Error> {
+    Command::bwr(register::AL_CONTROL)
+        .ignore_wkc()
+        .send(maindevice, u16::from(al::INIT) | al::CONTROL_ACKNOWLEDGE)
+        .await
+}
+
+impl<const MAX: usize, const PDI: usize> Teardown for SubDeviceGroup<MAX, PDI, Op> {
+    type Init = SubDeviceGroup<MAX, PDI, Init>;
+
+    async fn teardown(self, maindevice: &MainDevice<'_>) -> Result<Self::Init, Error> {
+        let group = step(
+            self.into_safe_op(maindevice).await,
+            "OP",
+            "SAFE-OP",
+            maindevice,
+        )
+        .await?;
+        group.teardown(maindevice).await
+    }
+}
+
+impl<const MAX: usize, const PDI: usize> Teardown for SubDeviceGroup<MAX, PDI, SafeOp> {
+    type Init = SubDeviceGroup<MAX, PDI, Init>;
+
+    async fn teardown(self, maindevice: &MainDevice<'_>) -> Result<Self::Init, Error> {
+        let group = step(
+            self.into_pre_op(maindevice).await,
+            "SAFE-OP",
+            "PRE-OP",
+            maindevice,
+        )
+        .await?;
+        group.teardown(maindevice).await
+    }
+}
+
+impl<const MAX: usize, const PDI: usize> Teardown for SubDeviceGroup<MAX, PDI, PreOp> {
+    type Init = SubDeviceGroup<MAX, PDI, Init>;
+
+    async fn teardown(self, maindevice: &MainDevice<'_>) -> Result<Self::Init, Error> {
+        step(
+            self.into_init(maindevice).await,
+            "PRE-OP",
+            "INIT",
+            maindevice,
+        )
+        .await
+    }
+}
+
+impl<const MAX: usize, const PDI: usize> Teardown for SubDeviceGroup<MAX, PDI, Init> {
+    type Init = Self;
+
+    async fn teardown(self, _maindevice: &MainDevice<'_>) -> Result<Self::Init, Error> {
+        Ok(self)
+    }
+}
+
+/// Holds a group and tears it down to INIT if it's dropped without being
+/// taken back, e.g. on an early return or a panic.
+///
+/// Dropping blocks on the teardown, so this must live inside a
+/// multi-threaded tokio runtime.
+pub struct TeardownGuard<'a, G: Teardown> {
+    group: Option<G>,
+    maindevice: Arc<MainDevice<'a>>,
+}
+
+impl<'a, G: Teardown> TeardownGuard<'a, G> {
+    pub fn new(group: G, maindevice: Arc<MainDevice<'a>>) -> Self {
+        Self {
+            group: Some(group),
+            maindevice,
+        }
+    }
+
+    /// Take the group back, e.g. to transition it, without tearing it down.
+    pub fn into_inner(mut self) -> G {
+        self.group.take().expect("group is only taken once")
+    }
+}
+
+impl<G: Teardown> Deref for TeardownGuard<'_, G> {
+    type Target = G;
+
+    fn deref(&self) -> &G {
+        self.group.as_ref().expect("group is only taken once")
+    }
+}
+
+impl<G: Teardown> Drop for TeardownGuard<'_, G> {
+    fn drop(&mut self) {
+        let Some(group) = self.group.take() else {
+            return;
+        };
+        let maindevice = &self.maindevice;
+        // Errors are already reported by the teardown itself.
+        let _ = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(into_init(group, maindevice))
+        });
+    }
+}
@@ -0,0 +1,844 @@
+//! Read and decode a SubDevice's SII EEPROM.
+
+use std::{fmt, future::Future, time::Duration};
+
+use ethercrab::{error::Error, SubDeviceIdentity, SubDeviceRef};
+use tokio::time::Instant;
+
+use crate::register;
+
+/// How long a single EEPROM command may take.
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+const COMMAND_READ: u16 = 0x0100;
+/// Write, with the write enable bit set.
+const COMMAND_WRITE: u16 = 0x0201;
+const STATUS_READ_8_BYTES: u16 = 1 << 6;
+const STATUS_COMMAND_ERROR: u16 = 1 << 13;
+const STATUS_WRITE_ENABLE_ERROR: u16 = 1 << 14;
+const STATUS_BUSY: u16 = 1 << 15;
+const CONFIG_OFFER_TO_PDI: u8 = 1 << 0;
+/// Takes the EEPROM back from the PDI whether it's done with it or not.
+const CONFIG_FORCE_ECAT_ACCESS: u8 = 1 << 1;
+const PDI_ACCESS_TAKEN: u8 = 1 << 0;
+/// How often to check whether the PDI has let go of the EEPROM.
+const ACCESS_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+const ALIAS_WORD: usize = 4;
+const CHECKSUM_WORD: usize = 7;
+/// Word address of the first category.
+const CATEGORIES_START: u16 = 0x0040;
+const CATEGORY_STRINGS: u16 = 10;
+const CATEGORY_GENERAL: u16 = 30;
+const CATEGORY_FMMU: u16 = 40;
+const CATEGORY_SYNC_MANAGER: u16 = 41;
+const CATEGORY_TX_PDO: u16 = 50;
+const CATEGORY_RX_PDO: u16 = 51;
+const CATEGORY_END: u16 = 0xffff;
+
+#[derive(Debug)]
+pub enum SiiError {
+    Bus(Error),
+    /// The ESC reported an error; holds the EEPROM control/status register.
+    Eeprom(u16),
+    /// The EEPROM stayed busy for too long.
+    Timeout,
+    /// The device's PDI, e.g. its microcontroller, kept hold of the EEPROM.
+    PdiAccess,
+    /// The contents don't follow the SII layout.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for SiiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bus(err) => write!(f, "{err}"),
+            Self::Eeprom(status) => write!(f, "EEPROM error, status {status:#06x}"),
+            Self::Timeout => write!(f, "EEPROM stayed busy for more than {TIMEOUT:?}"),
+            Self::PdiAccess => write!(f, "the device's PDI has the EEPROM and didn't let go"),
+            Self::Malformed(what) => write!(f, "malformed SII: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for SiiError {}
+
+impl From<Error> for SiiError {
+    fn from(err: Error) -> Self {
+        Self::Bus(err)
+    }
+}
+
+/// Take the EEPROM from the device's PDI, which shares it on devices with a
+/// microcontroller, waiting up to `timeout` for the PDI to let go, or
+/// taking it straight away if `force`. Returns the EEPROM configuration to
+/// hand to [`release`] when done.
+pub async fn acquire<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    timeout: Duration,
+    force: bool,
+) -> Result<u8, SiiError> {
+    let config: u8 = subdevice.register_read(register::SII_CONFIG).await?;
+    // Stop offering it first, so the PDI doesn't take it again once it's
+    // let go.
+    subdevice.register_write(register::SII_CONFIG, 0u8).await?;
+    if force {
+        subdevice
+            .register_write(register::SII_CONFIG, CONFIG_FORCE_ECAT_ACCESS)
+            .await?;
+    }
+    let start = Instant::now();
+    loop {
+        let access: u8 = subdevice.register_read(register::SII_PDI_ACCESS).await?;
+        if access & PDI_ACCESS_TAKEN == 0 {
+            return Ok(config);
+        }
+        if start.elapsed() > timeout {
+            // Leave it as it was, rather than withheld from a PDI that
+            // still thinks it has it.
+            release(subdevice, config).await?;
+            return Err(SiiError::PdiAccess);
+        }
+        tokio::time::sleep(ACCESS_POLL_INTERVAL).await;
+    }
+}
+
+/// Offer the EEPROM back to the PDI if it was before [`acquire`].
+pub async fn release<S>(subdevice: &SubDeviceRef<'_, S>, config: u8) -> Result<(), SiiError> {
+    subdevice
+        .register_write(register::SII_CONFIG, config & CONFIG_OFFER_TO_PDI)
+        .await?;
+    Ok(())
+}
+
+/// Run `access` with the EEPROM taken from the device's PDI, as
+/// [`acquire`] does without forcing, and hand it back afterwards whether
+/// `access` succeeded or not.
+pub async fn with_access<S, T>(
+    subdevice: &SubDeviceRef<'_, S>,
+    timeout: Duration,
+    access: impl Future<Output = Result<T, SiiError>>,
+) -> Result<T, SiiError> {
+    let config = acquire(subdevice, timeout, false).await?;
+    let result = access.await;
+    let released = release(subdevice, config).await;
+    let value = result?;
+    released?;
+    Ok(value)
+}
+
+/// Wait for the EEPROM interface to finish its current command, returning
+/// the control/status register.
+async fn wait_idle<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<u16, SiiError> {
+    let start = Instant::now();
+    loop {
+        let status: u16 = subdevice.register_read(register::SII_CONTROL).await?;
+        if status & STATUS_BUSY == 0 {
+            return Ok(status);
+        }
+        if start.elapsed() > TIMEOUT {
+            return Err(SiiError::Timeout);
+        }
+    }
+}
+
+/// Start an EEPROM command at `word_address` and wait for it to finish.
+async fn command<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    command: u16,
+    word_address: u32,
+) -> Result<u16, SiiError> {
+    wait_idle(subdevice).await?;
+    let [c0, c1] = command.to_le_bytes();
+    let [a0, a1, a2, a3] = word_address.to_le_bytes();
+    // Control and address are adjacent, so one write starts the command.
+    subdevice
+        .register_write(register::SII_CONTROL, [c0, c1, a0, a1, a2, a3])
+        .await?;
+    let status = wait_idle(subdevice).await?;
+    if status & (STATUS_COMMAND_ERROR | STATUS_WRITE_ENABLE_ERROR) != 0 {
+        return Err(SiiError::Eeprom(status));
+    }
+    Ok(status)
+}
+
+/// Read `len` bytes of EEPROM starting at `word_address`.
+pub async fn read<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    word_address: u16,
+    len: usize,
+) -> Result<Vec<u8>, SiiError> {
+    let mut data = Vec::with_capacity(len + 8);
+    let mut address = u32::from(word_address);
+    while data.len() < len {
+        let status = command(subdevice, COMMAND_READ, address).await?;
+        let chunk: [u8; 8] = subdevice.register_read(register::SII_DATA).await?;
+        let chunk_len = if status & STATUS_READ_8_BYTES != 0 {
+            8
+        } else {
+            4
+        };
+        data.extend_from_slice(&chunk[..chunk_len]);
+        address += chunk_len as u32 / 2;
+    }
+    data.truncate(len);
+    Ok(data)
+}
+
+/// Write `data` to the EEPROM starting at `word_address`, one word at a
+/// time.
+pub async fn write<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    word_address: u16,
+    data: &[u8],
+) -> Result<(), SiiError> {
+    if !data.len().is_multiple_of(2) {
+        return Err(SiiError::Malformed("not a whole number of words"));
+    }
+    for (offset, word) in data.chunks(2).enumerate() {
+        wait_idle(subdevice).await?;
+        subdevice
+            .register_write(register::SII_DATA, [word[0], word[1]])
+            .await?;
+        command(
+            subdevice,
+            COMMAND_WRITE,
+            u32::from(word_address) + offset as u32,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Write `data` to the EEPROM from word 0, skipping the words that already
+/// match `current`, and return how many words were written.
+pub async fn write_changed<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    current: &[u8],
+    data: &[u8],
+) -> Result<usize, SiiError> {
+    if !data.len().is_multiple_of(2) {
+        return Err(SiiError::Malformed("not a whole number of words"));
+    }
+    let mut written = 0;
+    for (offset, word) in data.chunks(2).enumerate() {
+        if current.get(offset * 2..offset * 2 + 2) == Some(word) {
+            continue;
+        }
+        write(subdevice, offset as u16, word).await?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Set the station alias in the EEPROM header, updating its checksum. The
+/// ESC only picks it up at power-up.
+pub async fn write_alias<S>(subdevice: &SubDeviceRef<'_, S>, alias: u16) -> Result<(), SiiError> {
+    let current = read(subdevice, 0, 16).await?;
+    let mut header = current.clone();
+    header[ALIAS_WORD * 2..ALIAS_WORD * 2 + 2].copy_from_slice(&alias.to_le_bytes());
+    header[CHECKSUM_WORD * 2] = header_checksum(&header);
+    write_changed(subdevice, &current, &header).await?;
+    Ok(())
+}
+
+/// Read and decode only the fixed words at the start of the EEPROM.
+pub async fn read_header<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<Header, SiiError> {
+    Header::parse(&read(subdevice, 0, usize::from(CATEGORIES_START) * 2).await?)
+}
+
+/// Read the EEPROM up to and including the end-of-categories marker.
+pub async fn read_image<S>(subdevice: &SubDeviceRef<'_, S>) -> Result<Vec<u8>, SiiError> {
+    let mut image = read(subdevice, 0, usize::from(CATEGORIES_START) * 2).await?;
+    let size = Header::parse(&image)?.size;
+    loop {
+        let word = (image.len() / 2) as u16;
+        let header = read(subdevice, word, 4).await?;
+        image.extend_from_slice(&header);
+        let kind = u16::from_le_bytes([header[0], header[1]]);
+        if kind == CATEGORY_END {
+            return Ok(image);
+        }
+        let len = usize::from(u16::from_le_bytes([header[2], header[3]])) * 2;
+        if image.len() + len > size {
+            return Err(SiiError::Malformed(
+                "categories run past the end of the EEPROM",
+            ));
+        }
+        image.extend(read(subdevice, word + 2, len).await?);
+    }
+}
+
+/// The CRC-8 stored in word 7, covering words 0 to 6.
+pub fn header_checksum(image: &[u8]) -> u8 {
+    image[..14].iter().fold(0xff, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn word(image: &[u8], index: usize) -> u16 {
+    u16::from_le_bytes([image[index * 2], image[index * 2 + 1]])
+}
+
+fn dword(image: &[u8], index: usize) -> u32 {
+    u32::from(word(image, index)) | u32::from(word(image, index + 1)) << 16
+}
+
+/// The offsets and sizes of a pair of mailboxes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mailbox {
+    /// MainDevice to SubDevice.
+    pub receive_offset: u16,
+    pub receive_size: u16,
+    /// SubDevice to MainDevice.
+    pub send_offset: u16,
+    pub send_size: u16,
+}
+
+/// The fixed words at the start of the EEPROM.
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub pdi_control: u16,
+    pub pdi_config: u16,
+    pub alias: u16,
+    pub checksum_ok: bool,
+    pub identity: SubDeviceIdentity,
+    pub bootstrap_mailbox: Mailbox,
+    pub standard_mailbox: Mailbox,
+    /// Bit flags: AoE, EoE, CoE, FoE, SoE, VoE from bit 0.
+    pub mailbox_protocols: u16,
+    /// EEPROM size in bytes.
+    pub size: usize,
+    pub version: u16,
+}
+
+impl Header {
+    pub fn parse(image: &[u8]) -> Result<Self, SiiError> {
+        if image.len() < usize::from(CATEGORIES_START) * 2 {
+            return Err(SiiError::Malformed("shorter than the header"));
+        }
+        let mailbox = |start| Mailbox {
+            receive_offset: word(image, start),
+            receive_size: word(image, start + 1),
+            send_offset: word(image, start + 2),
+            send_size: word(image, start + 3),
+        };
+        Ok(Self {
+            pdi_control: word(image, 0x00),
+            pdi_config: word(image, 0x01),
+            alias: word(image, ALIAS_WORD),
+            checksum_ok: header_checksum(image) == image[CHECKSUM_WORD * 2],
+            identity: SubDeviceIdentity {
+                vendor_id: dword(image, 0x08),
+                product_id: dword(image, 0x0a),
+                revision: dword(image, 0x0c),
+                serial: dword(image, 0x0e),
+            },
+            bootstrap_mailbox: mailbox(0x14),
+            standard_mailbox: mailbox(0x18),
+            mailbox_protocols: word(image, 0x1c),
+            size: (usize::from(word(image, 0x3e)) + 1) * 1024 / 8,
+            version: word(image, 0x3f),
+        })
+    }
+
+    /// Names of the mailbox protocols the device supports.
+    pub fn protocol_names(&self) -> Vec<&'static str> {
+        ["AoE", "EoE", "CoE", "FoE", "SoE", "VoE"]
+            .into_iter()
+            .enumerate()
+            .filter(|(bit, _)| self.mailbox_protocols & (1 << bit) != 0)
+            .map(|(_, name)| name)
+            .collect()
+    }
+}
+
+/// The General category.
+#[derive(Debug, Clone)]
+pub struct General {
+    pub group: Option<String>,
+    pub image: Option<String>,
+    pub order: Option<String>,
+    pub name: Option<String>,
+    pub coe_details: u8,
+    pub foe_details: u8,
+    pub eoe_details: u8,
+    pub flags: u8,
+    /// Current drawn from the E-bus in mA; negative if fed in.
+    pub ebus_current: i16,
+    /// Physical layer of ports 0-3, four bits each.
+    pub physical_ports: u16,
+}
+
+/// What an FMMU is used for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FmmuUsage {
+    Unused,
+    Outputs,
+    Inputs,
+    MailboxState,
+    Other(u8),
+}
+
+impl From<u8> for FmmuUsage {
+    fn from(byte: u8) -> Self {
+        match byte {
+            0 | 0xff => Self::Unused,
+            1 => Self::Outputs,
+            2 => Self::Inputs,
+            3 => Self::MailboxState,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl fmt::Display for FmmuUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unused => write!(f, "unused"),
+            Self::Outputs => write!(f, "outputs"),
+            Self::Inputs => write!(f, "inputs"),
+            Self::MailboxState => write!(f, "mailbox-state"),
+            Self::Other(other) => write!(f, "{other:#04x}"),
+        }
+    }
+}
+
+/// An entry in the SyncM category.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncManager {
+    pub start_address: u16,
+    pub length: u16,
+    pub control: u8,
+    pub enable: u8,
+    /// 1 mailbox out, 2 mailbox in, 3 outputs, 4 inputs, 0 unused.
+    pub kind: u8,
+}
+
+impl SyncManager {
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            1 => "mbx-out",
+            2 => "mbx-in",
+            3 => "outputs",
+            4 => "inputs",
+            _ => "unused",
+        }
+    }
+}
+
+/// A PDO from the TXPDO or RXPDO category.
+#[derive(Debug, Clone)]
+pub struct Pdo {
+    pub index: u16,
+    pub sync_manager: u8,
+    pub dc_sync: u8,
+    pub name: Option<String>,
+    pub flags: u16,
+    pub entries: Vec<PdoEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PdoEntry {
+    pub index: u16,
+    pub sub_index: u8,
+    pub name: Option<String>,
+    pub data_type: u8,
+    pub bit_len: u8,
+    pub flags: u16,
+}
+
+/// A decoded SII EEPROM image.
+#[derive(Debug, Clone)]
+pub struct Sii {
+    pub header: Header,
+    pub strings: Vec<String>,
+    pub general: Option<General>,
+    pub fmmus: Vec<FmmuUsage>,
+    pub sync_managers: Vec<SyncManager>,
+    pub tx_pdos: Vec<Pdo>,
+    pub rx_pdos: Vec<Pdo>,
+    /// Categories that aren't decoded, by type.
+    pub other: Vec<(u16, Vec<u8>)>,
+}
+
+impl Sii {
+    pub fn parse(image: &[u8]) -> Result<Self, SiiError> {
+        let header = Header::parse(image)?;
+
+        let mut categories = vec![];
+        let mut offset = usize::from(CATEGORIES_START) * 2;
+        loop {
+            let Some(category) = image.get(offset..offset + 4) else {
+                return Err(SiiError::Malformed("no end-of-categories marker"));
+            };
+            let kind = u16::from_le_bytes([category[0], category[1]]);
+            if kind == CATEGORY_END {
+                break;
+            }
+            let len = usize::from(u16::from_le_bytes([category[2], category[3]])) * 2;
+            let data = image
+                .get(offset + 4..offset + 4 + len)
+                .ok_or(SiiError::Malformed(
+                    "category runs past the end of the image",
+                ))?;
+            // Bit 15 marks vendor specific categories.
+            categories.push((kind & 0x7fff, data));
+            offset += 4 + len;
+        }
+
+        let strings = match categories
+            .iter()
+            .find(|(kind, _)| *kind == CATEGORY_STRINGS)
+        {
+            Some((_, data)) => parse_strings(data)?,
+            None => vec![],
+        };
+        let string = |index: u8| {
+            usize::from(index)
+                .checked_sub(1)
+                .and_then(|index| strings.get(index))
+                .cloned()
+        };
+
+        let mut sii = Self {
+            header,
+            strings: strings.clone(),
+            general: None,
+            fmmus: vec![],
+            sync_managers: vec![],
+            tx_pdos: vec![],
+            rx_pdos: vec![],
+            other: vec![],
+        };
+        for (kind, data) in categories {
+            match kind {
+                CATEGORY_STRINGS => {}
+                CATEGORY_GENERAL => {
+                    if data.len() < 18 {
+                        return Err(SiiError::Malformed("General category too short"));
+                    }
+                    sii.general = Some(General {
+                        group: string(data[0]),
+                        image: string(data[1]),
+                        order: string(data[2]),
+                        name: string(data[3]),
+                        coe_details: data[5],
+                        foe_details: data[6],
+                        eoe_details: data[7],
+                        flags: data[11],
+                        ebus_current: i16::from_le_bytes([data[12], data[13]]),
+                        physical_ports: u16::from_le_bytes([data[16], data[17]]),
+                    });
+                }
+                CATEGORY_FMMU => sii.fmmus = data.iter().copied().map(FmmuUsage::from).collect(),
+                CATEGORY_SYNC_MANAGER => {
+                    sii.sync_managers = data
+                        .chunks_exact(8)
+                        .map(|sm| SyncManager {
+                            start_address: u16::from_le_bytes([sm[0], sm[1]]),
+                            length: u16::from_le_bytes([sm[2], sm[3]]),
+                            control: sm[4],
+                            enable: sm[6],
+                            kind: sm[7],
+                        })
+                        .collect();
+                }
+                CATEGORY_TX_PDO => sii.tx_pdos = parse_pdos(data, &string)?,
+                CATEGORY_RX_PDO => sii.rx_pdos = parse_pdos(data, &string)?,
+                other => sii.other.push((other, data.to_vec())),
+            }
+        }
+        Ok(sii)
+    }
+}
+
+fn parse_strings(data: &[u8]) -> Result<Vec<String>, SiiError> {
+    let malformed = || SiiError::Malformed("Strings category too short");
+    let (&count, mut rest) = data.split_first().ok_or_else(malformed)?;
+    let mut strings = vec![];
+    for _ in 0..count {
+        let (&len, tail) = rest.split_first().ok_or_else(malformed)?;
+        let string = tail.get(..usize::from(len)).ok_or_else(malformed)?;
+        strings.push(String::from_utf8_lossy(string).into());
+        rest = &tail[usize::from(len)..];
+    }
+    Ok(strings)
+}
+
+fn parse_pdos(data: &[u8], string: &impl Fn(u8) -> Option<String>) -> Result<Vec<Pdo>, SiiError> {
+    let mut pdos = vec![];
+    let mut rest = data;
+    while rest.len() >= 8 {
+        let entries = usize::from(rest[2]);
+        let entry_data = rest
+            .get(8..8 + entries * 8)
+            .ok_or(SiiError::Malformed("PDO entries run past the category"))?;
+        pdos.push(Pdo {
+            index: u16::from_le_bytes([rest[0], rest[1]]),
+            sync_manager: rest[3],
+            dc_sync: rest[4],
+            name: string(rest[5]),
+            flags: u16::from_le_bytes([rest[6], rest[7]]),
+            entries: entry_data
+                .chunks_exact(8)
+                .map(|entry| PdoEntry {
+                    index: u16::from_le_bytes([entry[0], entry[1]]),
+                    sub_index: entry[2],
+                    name: string(entry[3]),
+                    data_type: entry[4],
+                    bit_len: entry[5],
+                    flags: u16::from_le_bytes([entry[6], entry[7]]),
+                })
+                .collect(),
+        });
+        rest = &rest[8 + entries * 8..];
+    }
+    Ok(pdos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header with alias 0x10, and a checksum worked out by hand.
+    fn header() -> Vec<u8> {
+        let mut image = vec![0; usize::from(CATEGORIES_START) * 2];
+        let mut put = |index: usize, value: u16| {
+            image[index * 2..index * 2 + 2].copy_from_slice(&value.to_le_bytes());
+        };
+        put(0x00, 0x0c80);
+        put(0x01, 0x6e88);
+        put(ALIAS_WORD, 0x0010);
+        put(CHECKSUM_WORD, 0x00bf);
+        // Vendor, product, revision, and serial.
+        put(0x08, 0x0002);
+        put(0x0a, 0x3052);
+        put(0x0b, 0x0c1e);
+        put(0x0d, 0x0014);
+        put(0x0e, 1234);
+        // Standard mailbox: out at 0x1000, in at 0x1080, 128 bytes each.
+        put(0x18, 0x1000);
+        put(0x19, 128);
+        put(0x1a, 0x1080);
+        put(0x1b, 128);
+        put(0x1c, 0b100);
+        // 2 kbit, i.e. 256 bytes.
+        put(0x3e, 0x0001);
+        put(0x3f, 1);
+        image
+    }
+
+    fn category(image: &mut Vec<u8>, kind: u16, mut data: Vec<u8>) {
+        if !data.len().is_multiple_of(2) {
+            data.push(0);
+        }
+        image.extend(kind.to_le_bytes());
+        image.extend((data.len() as u16 / 2).to_le_bytes());
+        image.extend(data);
+    }
+
+    /// The end marker, and the blank EEPROM after it.
+    fn end(image: &mut Vec<u8>) {
+        image.extend(CATEGORY_END.to_le_bytes());
+        image.extend([0xff; 2]);
+    }
+
+    fn image() -> Vec<u8> {
+        let mut image = header();
+        category(
+            &mut image,
+            CATEGORY_STRINGS,
+            [&[3][..], &[6], b"EL3102", &[6], b"Inputs", &[5], b"Value"].concat(),
+        );
+        let mut general = vec![0; 32];
+        general[2] = 1; // order
+        general[3] = 1; // name
+        general[5] = 0x23;
+        general[11] = 0x04;
+        general[12..14].copy_from_slice(&(-100i16).to_le_bytes());
+        general[16..18].copy_from_slice(&0x0011u16.to_le_bytes());
+        category(&mut image, CATEGORY_GENERAL, general);
+        category(&mut image, CATEGORY_FMMU, vec![2, 3, 0xff, 0]);
+        category(
+            &mut image,
+            CATEGORY_SYNC_MANAGER,
+            [
+                [0x00, 0x10, 0x80, 0x00, 0x26, 0x00, 0x01, 0x01],
+                [0x80, 0x11, 0x04, 0x00, 0x20, 0x00, 0x01, 0x04],
+            ]
+            .concat(),
+        );
+        category(
+            &mut image,
+            CATEGORY_TX_PDO,
+            [
+                // 0x1a00, 2 entries, SM 3, no DC sync, named "Inputs".
+                [0x00, 0x1a, 2, 3, 0, 2, 0, 0],
+                // 0x6000:11, "Value", INT, 16 bits.
+                [0x00, 0x60, 0x11, 3, 0x03, 16, 0, 0],
+                // A 16 bit gap.
+                [0x00, 0x00, 0x00, 0, 0x00, 16, 0, 0],
+            ]
+            .concat(),
+        );
+        category(&mut image, 0x8001, vec![0xaa, 0xbb]);
+        end(&mut image);
+        image
+    }
+
+    #[test]
+    fn checksum() {
+        let image = header();
+        assert_eq!(header_checksum(&image), 0xbf);
+        assert!(Header::parse(&image).unwrap().checksum_ok);
+
+        let mut corrupted = image;
+        corrupted[2] ^= 1;
+        assert!(!Header::parse(&corrupted).unwrap().checksum_ok);
+    }
+
+    #[test]
+    fn parse_header() {
+        let header = Header::parse(&header()).unwrap();
+        assert_eq!(header.pdi_control, 0x0c80);
+        assert_eq!(header.pdi_config, 0x6e88);
+        assert_eq!(header.alias, 0x10);
+        assert_eq!(
+            header.identity,
+            SubDeviceIdentity {
+                vendor_id: 0x2,
+                product_id: 0x0c1e3052,
+                revision: 0x0014_0000,
+                serial: 1234,
+            }
+        );
+        assert_eq!(
+            header.standard_mailbox,
+            Mailbox {
+                receive_offset: 0x1000,
+                receive_size: 128,
+                send_offset: 0x1080,
+                send_size: 128,
+            }
+        );
+        assert_eq!(header.protocol_names(), ["CoE"]);
+        assert_eq!(header.size, 256);
+        assert_eq!(header.version, 1);
+
+        assert!(Header::parse(&[0; 64]).is_err());
+    }
+
+    #[test]
+    fn parse_categories() {
+        let sii = Sii::parse(&image()).unwrap();
+        assert_eq!(sii.strings, ["EL3102", "Inputs", "Value"]);
+
+        let general = sii.general.unwrap();
+        assert_eq!(general.group, None);
+        assert_eq!(general.image, None);
+        assert_eq!(general.order.as_deref(), Some("EL3102"));
+        assert_eq!(general.name.as_deref(), Some("EL3102"));
+        assert_eq!(general.coe_details, 0x23);
+        assert_eq!(general.flags, 0x04);
+        assert_eq!(general.ebus_current, -100);
+        assert_eq!(general.physical_ports, 0x0011);
+
+        assert_eq!(
+            sii.fmmus,
+            [
+                FmmuUsage::Inputs,
+                FmmuUsage::MailboxState,
+                FmmuUsage::Unused,
+                FmmuUsage::Unused,
+            ]
+        );
+
+        assert_eq!(
+            sii.sync_managers,
+            [
+                SyncManager {
+                    start_address: 0x1000,
+                    length: 128,
+                    control: 0x26,
+                    enable: 1,
+                    kind: 1,
+                },
+                SyncManager {
+                    start_address: 0x1180,
+                    length: 4,
+                    control: 0x20,
+                    enable: 1,
+                    kind: 4,
+                },
+            ]
+        );
+        assert_eq!(sii.sync_managers[1].kind_name(), "inputs");
+
+        let [pdo] = sii.tx_pdos.as_slice() else {
+            panic!("expected one TxPDO");
+        };
+        assert_eq!(pdo.index, 0x1a00);
+        assert_eq!(pdo.sync_manager, 3);
+        assert_eq!(pdo.name.as_deref(), Some("Inputs"));
+        let entries: Vec<_> = pdo
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.index,
+                    entry.sub_index,
+                    entry.name.as_deref(),
+                    entry.data_type,
+                    entry.bit_len,
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [(0x6000, 0x11, Some("Value"), 0x03, 16), (0, 0, None, 0, 16)]
+        );
+        assert!(sii.rx_pdos.is_empty());
+
+        // Vendor categories are kept, without the vendor bit.
+        assert_eq!(sii.other, [(1, vec![0xaa, 0xbb])]);
+    }
+
+    #[test]
+    fn parse_malformed() {
+        let mut unterminated = image();
+        unterminated.truncate(unterminated.len() - 4);
+        assert!(matches!(
+            Sii::parse(&unterminated),
+            Err(SiiError::Malformed(_))
+        ));
+
+        let mut overrun = header();
+        overrun.extend([0x0a, 0x00, 0x10, 0x00, 0x01]);
+        assert!(matches!(Sii::parse(&overrun), Err(SiiError::Malformed(_))));
+
+        let mut short_general = header();
+        category(&mut short_general, CATEGORY_GENERAL, vec![0; 4]);
+        end(&mut short_general);
+        assert!(matches!(
+            Sii::parse(&short_general),
+            Err(SiiError::Malformed(_))
+        ));
+
+        let mut short_strings = header();
+        category(&mut short_strings, CATEGORY_STRINGS, vec![2, 4, b'a', b'b']);
+        end(&mut short_strings);
+        assert!(matches!(
+            Sii::parse(&short_strings),
+            Err(SiiError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+//! Work out how SubDevices are cabled together from their port link status.
+
+/// The order an ESC forwards a frame through its ports.
+const PORT_ORDER: [u8; 4] = [0, 3, 1, 2];
+
+/// The device and port a SubDevice is plugged into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Upstream {
+    /// Position of the upstream device on the bus.
+    pub index: usize,
+    pub port: u8,
+}
+
+/// Which of ports 0-3 are linked to another device, given the DL status
+/// register.
+pub fn open_ports(dl_status: u16) -> [bool; 4] {
+    std::array::from_fn(|port| {
+        let loop_closed = dl_status & (1 << (8 + 2 * port)) != 0;
+        let communicating = dl_status & (1 << (9 + 2 * port)) != 0;
+        !loop_closed && communicating
+    })
+}
+
+/// Find where each device is plugged in, given the open ports of every
+/// device in bus order. The first device has no upstream.
+///
+/// This assumes frames enter each device on port 0, which holds for
+/// anything wired with standard cabling.
+pub fn upstreams(open_ports: &[[bool; 4]]) -> Vec<Option<Upstream>> {
+    // Devices whose downstream ports haven't all been matched to a device
+    // yet, along with those ports in forwarding order.
+    let mut unfinished: Vec<(usize, Vec<u8>)> = vec![];
+    open_ports
+        .iter()
+        .enumerate()
+        .map(|(index, ports)| {
+            while unfinished.last().is_some_and(|(_, free)| free.is_empty()) {
+                unfinished.pop();
+            }
+            let upstream = unfinished.last_mut().map(|(parent, free)| Upstream {
+                index: *parent,
+                port: free.remove(0),
+            });
+            let downstream = PORT_ORDER[1..]
+                .iter()
+                .copied()
+                .filter(|&port| ports[port as usize])
+                .collect();
+            unfinished.push((index, downstream));
+            upstream
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_ports_from_dl_status() {
+        for (dl_status, expected) in [
+            (0x0000, [false; 4]),
+            // Ports 0 and 1 communicating, 2 and 3 closed.
+            (0x5a00, [true, true, false, false]),
+            // Communicating but closed isn't open.
+            (0x0300, [false; 4]),
+            (0xaa00, [true; 4]),
+            (0x8200, [true, false, false, true]),
+        ] {
+            assert_eq!(open_ports(dl_status), expected, "{dl_status:#06x}");
+        }
+    }
+
+    #[test]
+    fn upstreams_from_open_ports() {
+        const IN: [bool; 4] = [true, false, false, false];
+        const IN_OUT: [bool; 4] = [true, true, false, false];
+        const IN_OUT_BRANCH: [bool; 4] = [true, true, false, true];
+        const ALL: [bool; 4] = [true; 4];
+        let at = |index, port| Some(Upstream { index, port });
+        for (open_ports, expected) in [
+            (vec![], vec![]),
+            (vec![IN], vec![None]),
+            // A line.
+            (vec![IN_OUT, IN_OUT, IN], vec![None, at(0, 1), at(1, 1)]),
+            // A branch on port 3, which frames go through before port 1.
+            (
+                vec![IN_OUT_BRANCH, IN, IN_OUT, IN],
+                vec![None, at(0, 3), at(0, 1), at(2, 1)],
+            ),
+            // A junction using every port, with a line off port 3.
+            (
+                vec![ALL, IN_OUT, IN, IN, IN],
+                vec![None, at(0, 3), at(1, 1), at(0, 1), at(0, 2)],
+            ),
+        ] {
+            assert_eq!(upstreams(&open_ports), expected, "{open_ports:?}");
+        }
+    }
+}